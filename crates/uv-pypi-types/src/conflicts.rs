@@ -58,6 +58,95 @@ impl Conflicts {
         self.0.append(&mut other.0);
     }
 
+    /// Add a set of conflicting items.
+    ///
+    /// Duplicate items are collapsed. A set with fewer than two distinct
+    /// items can't express any mutual exclusion, so it is ignored.
+    pub fn add_set(&mut self, items: impl IntoIterator<Item = ConflictItem>) {
+        let set = items.into_iter().collect::<BTreeSet<_>>();
+        if set.len() < 2 {
+            return;
+        }
+        self.0.push(ConflictSet {
+            set,
+            is_inferred_conflict: false,
+            requires_one: false,
+        });
+    }
+
+    /// Returns the differences between these conflicts and `other`, e.g., to
+    /// attribute changes in a lockfile to edits of `tool.uv.conflicts`.
+    ///
+    /// Sets are compared by their items only, so the order in which sets and
+    /// items are declared doesn't matter. A set that is not in `other` as is
+    /// but shares items with a set in `other` is reported as changed (pairing
+    /// it with the set it shares the most items with); otherwise, it is
+    /// reported as removed. The remaining sets in `other` are reported as
+    /// added.
+    pub fn diff(&self, other: &Self) -> ConflictsDiff {
+        let mut before = self.0.iter().collect::<Vec<_>>();
+        let mut after = other.0.iter().collect::<Vec<_>>();
+
+        // Sets that are in both are unchanged.
+        before.retain(|old| {
+            if let Some(pos) = after.iter().position(|new| new.set == old.set) {
+                after.remove(pos);
+                false
+            } else {
+                true
+            }
+        });
+
+        let mut diff = ConflictsDiff::default();
+        for old in before {
+            let best = after
+                .iter()
+                .enumerate()
+                .map(|(pos, new)| (pos, old.set.intersection(&new.set).count()))
+                .filter(|(_, shared)| *shared > 0)
+                .max_by_key(|(pos, shared)| (*shared, std::cmp::Reverse(*pos)));
+            if let Some((pos, _)) = best {
+                diff.changed.push(ConflictSetChange {
+                    before: old.clone(),
+                    after: after.remove(pos).clone(),
+                });
+            } else {
+                diff.removed.push(old.clone());
+            }
+        }
+        diff.added = after.into_iter().cloned().collect();
+        diff
+    }
+
+    /// Removes every item for which the given predicate returns `false`, e.g.,
+    /// items referencing packages that aren't part of the current resolution.
+    ///
+    /// A set that is left with fewer than two items can't express any mutual
+    /// exclusion, so it is removed entirely.
+    pub fn retain(&mut self, f: impl Fn(&ConflictItem) -> bool) {
+        for set in &mut self.0 {
+            set.set.retain(&f);
+        }
+        self.0.retain(|set| set.set.len() >= 2);
+    }
+
+    /// Returns every set that requires exactly one of its items to be
+    /// activated, but for which none of its items are activated.
+    ///
+    /// The given predicate should return `true` for each item that is
+    /// activated in the install being validated. (Sets where more than one
+    /// item is activated are not reported here, since mutual exclusion is
+    /// enforced separately via conflict markers.)
+    pub fn requires_one_violations(
+        &self,
+        is_activated: impl Fn(&ConflictItem) -> bool,
+    ) -> Vec<&ConflictSet> {
+        self.iter()
+            .filter(|set| set.requires_one())
+            .filter(|set| !set.iter().any(&is_activated))
+            .collect()
+    }
+
     /// Expand [`Conflicts`]s to include all [`ConflictSet`]s that can
     /// be transitively inferred from group conflicts directly defined
     /// in configuration.
@@ -208,6 +297,58 @@ impl Conflicts {
     }
 }
 
+/// The differences between two [`Conflicts`], as returned by [`Conflicts::diff`].
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ConflictsDiff {
+    /// The sets that were added.
+    pub added: Vec<ConflictSet>,
+    /// The sets that were removed.
+    pub removed: Vec<ConflictSet>,
+    /// The sets whose items changed.
+    pub changed: Vec<ConflictSetChange>,
+}
+
+impl ConflictsDiff {
+    /// Returns true if the conflicts are the same, up to ordering.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A set of conflicts whose items changed between two [`Conflicts`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConflictSetChange {
+    /// The set before the change.
+    pub before: ConflictSet,
+    /// The set after the change.
+    pub after: ConflictSet,
+}
+
+/// A builder for [`Conflicts`], e.g., to assemble conflicts programmatically.
+#[derive(Debug, Default, Clone)]
+pub struct ConflictsBuilder(Conflicts);
+
+impl ConflictsBuilder {
+    /// Create a builder without any conflicts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a set of conflicting items.
+    ///
+    /// See [`Conflicts::add_set`].
+    #[must_use]
+    pub fn set(mut self, items: impl IntoIterator<Item = ConflictItem>) -> Self {
+        self.0.add_set(items);
+        self
+    }
+
+    /// Returns the conflicts built so far.
+    pub fn build(self) -> Conflicts {
+        self.0
+    }
+}
+
 /// A single set of package-extra pairs that conflict with one another.
 ///
 /// Within each set of conflicts, the resolver should isolate the requirements
@@ -217,10 +358,31 @@ impl Conflicts {
 ///
 /// A `TryFrom<Vec<ConflictItem>>` impl may be used to build a set from a
 /// sequence. Note though that at least 2 items are required.
-#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+///
+/// Whether a set requires one of its items to be activated is ignored when
+/// comparing or hashing sets: it is only validated at install time and
+/// doesn't affect the resolution, so it isn't recorded in the lock file
+/// either.
+#[derive(Debug, Default, Clone)]
 pub struct ConflictSet {
     set: BTreeSet<ConflictItem>,
     is_inferred_conflict: bool,
+    requires_one: bool,
+}
+
+impl PartialEq for ConflictSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.set == other.set && self.is_inferred_conflict == other.is_inferred_conflict
+    }
+}
+
+impl Eq for ConflictSet {}
+
+impl Hash for ConflictSet {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.set.hash(state);
+        self.is_inferred_conflict.hash(state);
+    }
 }
 
 impl ConflictSet {
@@ -229,6 +391,7 @@ impl ConflictSet {
         Self {
             set: BTreeSet::from_iter(vec![item1, item2]),
             is_inferred_conflict: false,
+            requires_one: false,
         }
     }
 
@@ -260,6 +423,24 @@ impl ConflictSet {
         self.is_inferred_conflict
     }
 
+    /// Returns true if exactly one item in this [`ConflictSet`] must be
+    /// activated, rather than at most one.
+    pub fn requires_one(&self) -> bool {
+        self.requires_one
+    }
+
+    /// Mark this [`ConflictSet`] as requiring exactly one of its items to be
+    /// activated.
+    ///
+    /// This doesn't change how mutual exclusion is encoded. It only marks the
+    /// set such that an install with none of its items activated can be
+    /// reported via [`Conflicts::requires_one_violations`].
+    #[must_use]
+    pub fn with_requires_one(mut self) -> Self {
+        self.requires_one = true;
+        self
+    }
+
     /// Replace an old [`ConflictItem`] with a new one.
     pub fn replaced_item(
         &self,
@@ -275,6 +456,7 @@ impl ConflictSet {
         Ok(Self {
             set: new_set,
             is_inferred_conflict: false,
+            requires_one: self.requires_one,
         })
     }
 
@@ -308,6 +490,7 @@ impl TryFrom<Vec<ConflictItem>> for ConflictSet {
         Ok(Self {
             set: BTreeSet::from_iter(items),
             is_inferred_conflict: false,
+            requires_one: false,
         })
     }
 }
@@ -611,7 +794,7 @@ impl SchemaConflicts {
         let mut conflicting = Conflicts::empty();
         for tool_uv_set in &self.0 {
             let mut set = vec![];
-            for item in &tool_uv_set.0 {
+            for item in &tool_uv_set.items {
                 let package = item.package.clone().unwrap_or_else(|| package.clone());
                 set.push(ConflictItem {
                     package: package.clone(),
@@ -621,7 +804,10 @@ impl SchemaConflicts {
             // OK because we guarantee that
             // `SchemaConflictingGroupList` is valid and there aren't
             // any new errors that can occur here.
-            let set = ConflictSet::try_from(set).unwrap();
+            let mut set = ConflictSet::try_from(set).unwrap();
+            if tool_uv_set.requires_one {
+                set = set.with_requires_one();
+            }
             conflicting.push(set);
         }
         conflicting
@@ -634,9 +820,23 @@ impl SchemaConflicts {
 /// schema format does not allow specifying the package name (or will make it
 /// optional in the future), where as the in-memory format needs the package
 /// name.
-#[derive(Debug, Default, Clone, Eq, PartialEq, serde::Serialize)]
-#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
-pub struct SchemaConflictSet(Vec<SchemaConflictItem>);
+#[derive(Debug, Default, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "SchemaConflictSetWire", into = "SchemaConflictSetWire")]
+pub struct SchemaConflictSet {
+    items: Vec<SchemaConflictItem>,
+    requires_one: bool,
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for SchemaConflictSet {
+    fn schema_name() -> Cow<'static, str> {
+        Cow::Borrowed("SchemaConflictSet")
+    }
+
+    fn json_schema(generator: &mut schemars::generate::SchemaGenerator) -> schemars::Schema {
+        <SchemaConflictSetWire as schemars::JsonSchema>::json_schema(generator)
+    }
+}
 
 /// Like [`ConflictItem`], but for deserialization in `pyproject.toml`.
 ///
@@ -668,16 +868,6 @@ impl schemars::JsonSchema for SchemaConflictItem {
     }
 }
 
-impl<'de> serde::Deserialize<'de> for SchemaConflictSet {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        let items = Vec::<SchemaConflictItem>::deserialize(deserializer)?;
-        Self::try_from(items).map_err(serde::de::Error::custom)
-    }
-}
-
 impl TryFrom<Vec<SchemaConflictItem>> for SchemaConflictSet {
     type Error = ConflictError;
 
@@ -687,10 +877,103 @@ impl TryFrom<Vec<SchemaConflictItem>> for SchemaConflictSet {
             1 => return Err(ConflictError::OneItem),
             _ => {}
         }
-        Ok(Self(items))
+        Ok(Self {
+            items,
+            requires_one: false,
+        })
+    }
+}
+
+impl TryFrom<SchemaConflictSetWire> for SchemaConflictSet {
+    type Error = ConflictError;
+
+    fn try_from(wire: SchemaConflictSetWire) -> Result<Self, ConflictError> {
+        match wire {
+            SchemaConflictSetWire::List(items) => Self::try_from(items),
+            SchemaConflictSetWire::Table(table) => Ok(Self {
+                requires_one: table.requires_one,
+                ..Self::try_from(table.items)?
+            }),
+        }
+    }
+}
+
+impl From<SchemaConflictSet> for SchemaConflictSetWire {
+    fn from(set: SchemaConflictSet) -> Self {
+        if set.requires_one {
+            Self::Table(SchemaConflictSetTable {
+                items: set.items,
+                requires_one: true,
+            })
+        } else {
+            Self::List(set.items)
+        }
+    }
+}
+
+/// A set of conflicting items, either as a list of items or as a table.
+///
+/// The table form can additionally require that exactly one of the items is activated, e.g.,
+/// `{ requires-one = true, items = [{ extra = "cpu" }, { extra = "gpu" }] }`.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+enum SchemaConflictSetWire {
+    List(Vec<SchemaConflictItem>),
+    Table(SchemaConflictSetTable),
+}
+
+impl<'de> serde::Deserialize<'de> for SchemaConflictSetWire {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = SchemaConflictSetWire;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a list of conflicting items or a table with `items`")
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let items = serde::de::Deserialize::deserialize(
+                    serde::de::value::SeqAccessDeserializer::new(seq),
+                )?;
+                Ok(SchemaConflictSetWire::List(items))
+            }
+
+            fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error>
+            where
+                M: serde::de::MapAccess<'de>,
+            {
+                let table = serde::de::Deserialize::deserialize(
+                    serde::de::value::MapAccessDeserializer::new(map),
+                )?;
+                Ok(SchemaConflictSetWire::Table(table))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
     }
 }
 
+/// A set of conflicting items, written as a table.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct SchemaConflictSetTable {
+    /// The conflicting items.
+    items: Vec<SchemaConflictItem>,
+    /// Whether exactly one of the items must be activated, rather than at most one.
+    #[serde(default)]
+    requires_one: bool,
+}
+
 /// A single item in a conflicting set.
 ///
 /// Each item is a pair of an (optional) package and a corresponding extra or group name for that
@@ -814,3 +1097,178 @@ pub struct Inference {
     pub included: bool,
     pub item: ConflictItem,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// Shortcut for creating a conflict item for an extra of `pkg`.
+    fn extra(name: &str) -> ConflictItem {
+        ConflictItem::from((
+            PackageName::from_str("pkg").unwrap(),
+            ExtraName::from_str(name).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn requires_one_violations() {
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(
+            ConflictSet::try_from(vec![extra("cpu"), extra("cu124")])
+                .unwrap()
+                .with_requires_one(),
+        );
+        conflicts.push(ConflictSet::try_from(vec![extra("foo"), extra("bar")]).unwrap());
+
+        // One backend is selected, so the set is satisfied.
+        let violations = conflicts.requires_one_violations(|item| *item == extra("cpu"));
+        assert!(violations.is_empty());
+
+        // No backend is selected. The `foo`/`bar` set doesn't require any of
+        // its items to be activated, so it isn't reported.
+        let violations = conflicts.requires_one_violations(|_| false);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].requires_one());
+        assert!(violations[0].contains_item(&extra("cpu")));
+        assert!(violations[0].contains_item(&extra("cu124")));
+    }
+
+    #[test]
+    fn requires_one_from_toml() {
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            conflicts: SchemaConflicts,
+        }
+
+        let toml = r#"
+            conflicts = [
+                { requires-one = true, items = [{ extra = "cpu" }, { extra = "cu124" }] },
+                [{ extra = "foo" }, { extra = "bar" }],
+            ]
+        "#;
+        let wrapper = toml_edit::de::from_str::<Wrapper>(toml).unwrap();
+        let conflicts = wrapper
+            .conflicts
+            .to_conflicts_with_package_name(&PackageName::from_str("pkg").unwrap());
+        let sets = conflicts.iter().collect::<Vec<_>>();
+        assert!(sets[0].requires_one());
+        assert!(!sets[1].requires_one());
+
+        // The flag doesn't affect the resolution, so it doesn't affect equality.
+        assert_eq!(
+            *sets[0],
+            ConflictSet::try_from(vec![extra("cpu"), extra("cu124")]).unwrap()
+        );
+
+        // The table form is validated like the list form.
+        let toml = r#"
+            conflicts = [{ requires-one = true, items = [{ extra = "cpu" }] }]
+        "#;
+        let err = toml_edit::de::from_str::<Wrapper>(toml).err().unwrap();
+        assert!(err.to_string().contains("at least two entries"));
+    }
+
+    #[test]
+    fn add_set() {
+        let mut conflicts = Conflicts::empty();
+        conflicts.add_set([extra("foo")]);
+        conflicts.add_set([extra("foo"), extra("foo")]);
+        assert!(conflicts.is_empty());
+
+        conflicts.add_set([extra("foo"), extra("bar"), extra("foo")]);
+        let sets = conflicts.iter().collect::<Vec<_>>();
+        assert_eq!(sets.len(), 1);
+        assert_eq!(
+            sets[0].iter().collect::<Vec<_>>(),
+            vec![&extra("bar"), &extra("foo")]
+        );
+    }
+
+    #[test]
+    fn builder_matches_toml() {
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            conflicts: Conflicts,
+        }
+
+        let toml = r#"
+            conflicts = [
+                [{ package = "pkg", extra = "foo" }, { package = "pkg", extra = "bar" }],
+                [{ package = "pkg", extra = "cpu" }, { package = "pkg", extra = "cu124" }],
+            ]
+        "#;
+        let parsed = toml_edit::de::from_str::<Wrapper>(toml).unwrap().conflicts;
+
+        let built = ConflictsBuilder::new()
+            .set([extra("foo"), extra("bar")])
+            .set([extra("cpu")])
+            .set([extra("cpu"), extra("cu124")])
+            .build();
+        assert_eq!(built, parsed);
+    }
+
+    #[test]
+    fn diff() {
+        let before = ConflictsBuilder::new()
+            .set([extra("foo"), extra("bar")])
+            .set([extra("cpu"), extra("cu124")])
+            .build();
+
+        // Reordering sets and items doesn't change anything.
+        let reordered = ConflictsBuilder::new()
+            .set([extra("cu124"), extra("cpu")])
+            .set([extra("bar"), extra("foo")])
+            .build();
+        assert!(before.diff(&reordered).is_empty());
+
+        let after = ConflictsBuilder::new()
+            .set([extra("cu128"), extra("cpu"), extra("cu124")])
+            .set([extra("bar"), extra("foo")])
+            .set([extra("a"), extra("b")])
+            .build();
+        let diff = before.diff(&after);
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.added,
+            vec![ConflictSet::try_from(vec![extra("a"), extra("b")]).unwrap()]
+        );
+        assert_eq!(
+            diff.changed,
+            vec![ConflictSetChange {
+                before: ConflictSet::try_from(vec![extra("cpu"), extra("cu124")]).unwrap(),
+                after: ConflictSet::try_from(vec![extra("cpu"), extra("cu124"), extra("cu128")])
+                    .unwrap(),
+            }]
+        );
+
+        // Going back reports the inverse.
+        let diff = after.diff(&before);
+        assert!(diff.added.is_empty());
+        assert_eq!(
+            diff.removed,
+            vec![ConflictSet::try_from(vec![extra("a"), extra("b")]).unwrap()]
+        );
+        assert_eq!(diff.changed.len(), 1);
+    }
+
+    #[test]
+    fn retain() {
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ConflictSet::try_from(vec![extra("foo"), extra("bar")]).unwrap());
+        conflicts.push(
+            ConflictSet::try_from(vec![extra("cpu"), extra("cu124"), extra("cu128")]).unwrap(),
+        );
+
+        conflicts.retain(|item| *item != extra("bar") && *item != extra("cu128"));
+
+        // The `foo`/`bar` set can no longer express a conflict, so it's removed.
+        let sets = conflicts.iter().collect::<Vec<_>>();
+        assert_eq!(sets.len(), 1);
+        assert_eq!(
+            sets[0].iter().collect::<Vec<_>>(),
+            vec![&extra("cpu"), &extra("cu124")]
+        );
+    }
+}
@@ -1,16 +1,23 @@
 use std::collections::BTreeSet;
 use std::collections::hash_map::Entry;
+use std::hash::{Hash, Hasher};
 
 use petgraph::graph::{EdgeIndex, NodeIndex};
-use petgraph::visit::EdgeRef;
+use petgraph::visit::{DfsEvent, EdgeFiltered, EdgeRef, depth_first_search};
 use petgraph::{Direction, Graph};
 use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 
-use uv_pep508::MarkerTree;
-use uv_pypi_types::{ConflictItem, Conflicts, Inference};
+use uv_cache_key::{CacheKey, CacheKeyHasher};
+use uv_distribution_types::RequiresPython;
+use uv_normalize::{ExtraName, GroupName, PackageName};
+use uv_pep508::{MarkerEnvironment, MarkerTree};
+use uv_pypi_types::{ConflictItem, ConflictSet, Conflicts, Inference};
 
+use crate::ResolveError;
 use crate::resolution::ResolutionGraphNode;
-use crate::universal_marker::UniversalMarker;
+use crate::universal_marker::{
+    ConflictEncoder, ConflictMarker, MarkerInterner, PairwiseConflictEncoder, UniversalMarker,
+};
 
 /// Determine the markers under which a package is reachable in the dependency tree.
 ///
@@ -21,324 +28,3362 @@ use crate::universal_marker::UniversalMarker;
 /// specific marker/longer path, so we don't update the node and don't re-queue it.
 pub(crate) fn marker_reachability<
     Marker: Boolean + Copy + PartialEq,
-    Node,
+    Node: GroupRoot,
     Edge: Reachable<Marker>,
 >(
     graph: &Graph<Node, Edge>,
     fork_markers: &[Edge],
+    group_selection: &GroupSelection,
 ) -> FxHashMap<NodeIndex, Marker> {
-    // Note that we build including the virtual packages due to how we propagate markers through
-    // the graph, even though we then only read the markers for base packages.
-    let mut reachability = FxHashMap::with_capacity_and_hasher(graph.node_count(), FxBuildHasher);
+    marker_reachability_bounded(graph, fork_markers, group_selection, None)
+        .unwrap_or_else(|_| unreachable!("marker reachability without a budget always converges"))
+}
 
-    // Collect the root nodes.
-    //
-    // Besides the actual virtual root node, virtual dev dependencies packages are also root
-    // nodes since the edges don't cover dev dependencies.
-    let mut queue: Vec<_> = graph
-        .node_indices()
-        .filter(|node_index| {
-            graph
-                .edges_directed(*node_index, Direction::Incoming)
-                .next()
-                .is_none()
-        })
-        .collect();
+/// The fixpoint iteration of [`marker_reachability`], which can be driven one relaxation at a
+/// time.
+pub(crate) struct ReachabilitySolver<'graph, Marker, Node, Edge> {
+    graph: &'graph Graph<Node, Edge>,
+    reachability: FxHashMap<NodeIndex, Marker>,
+    queue: Vec<NodeIndex>,
+}
 
-    // The root nodes are always applicable, unless the user has restricted resolver
-    // environments with `tool.uv.environments`.
-    let root_markers = if fork_markers.is_empty() {
-        Edge::true_marker()
-    } else {
-        fork_markers
-            .iter()
-            .fold(Edge::false_marker(), |mut acc, edge| {
-                acc.or(edge.marker());
-                acc
-            })
-    };
-    for root_index in &queue {
-        reachability.insert(*root_index, root_markers);
+impl<'graph, Marker: Boolean + Copy + PartialEq, Node, Edge: Reachable<Marker>>
+    ReachabilitySolver<'graph, Marker, Node, Edge>
+{
+    /// Seed the solver with the roots of the graph that are selected by `group_selection`.
+    pub(crate) fn new(
+        graph: &'graph Graph<Node, Edge>,
+        fork_markers: &[Edge],
+        group_selection: &GroupSelection,
+    ) -> Self
+    where
+        Node: GroupRoot,
+    {
+        // Only seed the roots selected by the user. Packages that are only reachable through
+        // excluded roots remain absent from the reachability map.
+        let root_markers = root_markers(fork_markers);
+        let seeds = selected_root_indices(graph, group_selection)
+            .into_iter()
+            .map(|root_index| (root_index, root_markers));
+
+        Self::from_seeds(graph, seeds)
     }
 
-    // Propagate all markers through the graph, so that the eventual marker for each node is the
-    // union of the markers of each path we can reach the node by.
-    while let Some(parent_index) = queue.pop() {
-        let marker = reachability[&parent_index];
-        for child_edge in graph.edges_directed(parent_index, Direction::Outgoing) {
+    /// Seed the solver with the given markers only, e.g., to seed a dependency group root with
+    /// `false` when the group isn't selected, or with a marker that restricts it to the
+    /// environments the group targets.
+    ///
+    /// Nodes without a seed don't contribute anything, even if they are roots of the graph. A
+    /// node that is only reachable through nodes seeded with `false` is unreachable, i.e., its
+    /// marker is `false`.
+    pub(crate) fn from_seeds(
+        graph: &'graph Graph<Node, Edge>,
+        seeds: impl IntoIterator<Item = (NodeIndex, Marker)>,
+    ) -> Self {
+        // Note that we build including the virtual packages due to how we propagate markers
+        // through the graph, even though we then only read the markers for base packages.
+        let mut reachability =
+            FxHashMap::with_capacity_and_hasher(graph.node_count(), FxBuildHasher);
+        let mut queue = Vec::new();
+        for (index, marker) in seeds {
+            reachability.insert(index, marker);
+            queue.push(index);
+        }
+        Self {
+            graph,
+            reachability,
+            queue,
+        }
+    }
+
+    /// Perform a single relaxation: pop a node from the queue and update the markers of its
+    /// children, re-queuing every child whose marker widened.
+    ///
+    /// Returns the node that was processed, or `None` once the fixpoint has been reached.
+    pub(crate) fn step(&mut self) -> Option<NodeIndex> {
+        let parent_index = self.queue.pop()?;
+        let marker = self.reachability[&parent_index];
+        for child_edge in self.graph.edges_directed(parent_index, Direction::Outgoing) {
+            // A child that is reachable in all environments is saturated: no other path can
+            // widen its marker, so there's nothing to relax.
+            if self
+                .reachability
+                .get(&child_edge.target())
+                .is_some_and(Boolean::is_true)
+            {
+                continue;
+            }
             // The marker for all paths to the child through the parent.
+            //
+            // Note that markers are handles to interned trees, so reading the edge's marker is
+            // a cheap copy rather than a clone of the tree.
             let mut child_marker = child_edge.weight().marker();
             child_marker.and(marker);
-            match reachability.entry(child_edge.target()) {
+            match self.reachability.entry(child_edge.target()) {
                 Entry::Occupied(mut existing) => {
                     // If the marker is a subset of the existing marker (A ⊆ B exactly if
                     // A ∪ B = A), updating the child wouldn't change child's marker.
+                    //
+                    // Markers are interned and canonical, so this comparison is an identity
+                    // check rather than a structural walk of the marker trees.
                     child_marker.or(*existing.get());
                     if &child_marker != existing.get() {
                         existing.insert(child_marker);
-                        queue.push(child_edge.target());
+                        self.queue.push(child_edge.target());
                     }
                 }
                 Entry::Vacant(vacant) => {
                     vacant.insert(child_marker);
-                    queue.push(child_edge.target());
+                    self.queue.push(child_edge.target());
                 }
             }
         }
+        Some(parent_index)
     }
 
-    reachability
-}
-
-/// Traverse the given dependency graph and propagate activated markers.
-///
-/// For example, given an edge like `foo[x1] -> bar`, then it is known that
-/// `x1` is activated. This in turn can be used to simplify any downstream
-/// conflict markers with `extra == "x1"` in them (by replacing `extra == "x1"`
-/// with `true`).
-pub(crate) fn simplify_conflict_markers(
-    conflicts: &Conflicts,
-    graph: &mut Graph<ResolutionGraphNode, UniversalMarker>,
-) {
-    // Do nothing if there are no declared conflicts. Without any declared
-    // conflicts, we know we have no conflict markers and thus nothing to
-    // simplify by determining which extras are activated at different points
-    // in the dependency graph.
-    if conflicts.is_empty() {
-        return;
+    /// The markers propagated so far.
+    #[cfg(test)]
+    pub(crate) fn reachability(&self) -> &FxHashMap<NodeIndex, Marker> {
+        &self.reachability
     }
 
-    // The set of activated extras and groups for each node. The ROOT nodes
-    // don't have any extras/groups activated.
-    let mut activated: FxHashMap<NodeIndex, Vec<FxHashSet<ConflictItem>>> = FxHashMap::default();
-
-    // Collect the root nodes.
-    //
-    // Besides the actual virtual root node, virtual dev dependencies packages are also root
-    // nodes since the edges don't cover dev dependencies.
-    let mut queue: Vec<_> = graph
-        .node_indices()
-        .filter(|node_index| {
-            graph
-                .edges_directed(*node_index, Direction::Incoming)
-                .next()
-                .is_none()
-        })
-        .collect();
-
-    let mut seen: FxHashSet<NodeIndex> = FxHashSet::default();
-    while let Some(parent_index) = queue.pop() {
-        if let Some((package, extra)) = graph[parent_index].package_extra_names() {
-            for set in activated
-                .entry(parent_index)
-                .or_insert_with(|| vec![FxHashSet::default()])
-            {
-                set.insert(ConflictItem::from((package.clone(), extra.clone())));
-            }
-        }
-        if let Some((package, group)) = graph[parent_index].package_group_names() {
-            for set in activated
-                .entry(parent_index)
-                .or_insert_with(|| vec![FxHashSet::default()])
-            {
-                set.insert(ConflictItem::from((package.clone(), group.clone())));
-            }
-        }
-        let sets = activated
-            .get(&parent_index)
-            .cloned()
-            .unwrap_or_else(|| vec![FxHashSet::default()]);
-        for child_edge in graph.edges_directed(parent_index, Direction::Outgoing) {
-            let mut change = false;
-            for set in sets.clone() {
-                let existing = activated.entry(child_edge.target()).or_default();
-                // This is doing a linear scan for testing membership, which
-                // is non-ideal. But it's not actually clear that there's a
-                // strictly better alternative without a real workload being
-                // slow because of this. Namely, we are checking whether the
-                // _set_ being inserted is equivalent to an existing set. So
-                // instead of, say, `Vec<FxHashSet<ConflictItem>>`, we could
-                // have `BTreeSet<BTreeSet<ConflictItem>>`. But this in turn
-                // makes mutating the elements in each set (done above) more
-                // difficult and likely require more allocations.
-                //
-                // So if this does result in a perf slowdown on some real
-                // work-load, I think the first step would be to re-examine
-                // whether we're doing more work than we need to be doing. If
-                // we aren't, then we might want a more purpose-built data
-                // structure for this.
-                if !existing.contains(&set) {
-                    existing.push(set);
-                    change = true;
-                }
-            }
-            if seen.insert(child_edge.target()) || change {
-                queue.push(child_edge.target());
-            }
-        }
+    /// The nodes that are queued for relaxation, with the next node to be processed last.
+    #[cfg(test)]
+    pub(crate) fn queue(&self) -> &[NodeIndex] {
+        &self.queue
     }
 
-    let mut inferences: FxHashMap<NodeIndex, Vec<BTreeSet<Inference>>> = FxHashMap::default();
-    for (node_id, sets) in activated {
-        let mut new_sets = Vec::with_capacity(sets.len());
-        for set in sets {
-            let mut new_set = BTreeSet::default();
-            for item in set {
-                for conflict_set in conflicts.iter() {
-                    if !conflict_set.contains(item.package(), item.as_ref().kind()) {
-                        continue;
-                    }
-                    for conflict_item in conflict_set.iter() {
-                        if conflict_item == &item {
-                            continue;
-                        }
-                        new_set.insert(Inference {
-                            item: conflict_item.clone(),
-                            included: false,
-                        });
-                    }
-                }
-                new_set.insert(Inference {
-                    item,
-                    included: true,
+    /// Run the fixpoint iteration until it converges, or until `max_relaxations` nodes have been
+    /// relaxed (see [`ReachabilitySolver::step`]).
+    pub(crate) fn solve(
+        mut self,
+        max_relaxations: Option<usize>,
+    ) -> Result<FxHashMap<NodeIndex, Marker>, ReachabilityNotConverged<Marker>> {
+        let mut relaxations = 0;
+        while !self.queue.is_empty() {
+            if max_relaxations.is_some_and(|max| relaxations >= max) {
+                return Err(ReachabilityNotConverged {
+                    relaxations,
+                    partial: self.reachability,
                 });
             }
-            new_sets.push(new_set);
+            self.step();
+            relaxations += 1;
         }
-        inferences.insert(node_id, new_sets);
+        Ok(self.reachability)
     }
 
-    for edge_index in (0..graph.edge_count()).map(EdgeIndex::new) {
-        let (from_index, to_index) = graph.edge_endpoints(edge_index).unwrap();
-        // If there are ambiguous edges (i.e., two or more edges
-        // with the same package name), then we specifically skip
-        // conflict marker simplification. It seems that in some
-        // cases, the logic encoded in `inferences` isn't quite enough
-        // to perfectly disambiguate between them. It's plausible we
-        // could do better here, but it requires smarter simplification
-        // logic. ---AG
-        let ambiguous_edges = graph
-            .edges_directed(from_index, Direction::Outgoing)
-            .filter(|edge| graph[to_index].package_name() == graph[edge.target()].package_name())
-            .count();
-        if ambiguous_edges > 1 {
-            continue;
-        }
-        let Some(inference_sets) = inferences.get(&from_index) else {
-            continue;
-        };
-        // If not all possible paths (represented by our inferences)
-        // satisfy the conflict marker on this edge, then we can't make any
-        // simplifications. Namely, because it follows that out inferences
-        // aren't always true. Some of them may sometimes be false.
-        let all_paths_satisfied = inference_sets.iter().all(|set| {
-            let extras = set
-                .iter()
-                .filter_map(|inf| {
-                    if !inf.included {
-                        return None;
-                    }
-                    Some((inf.item.package(), inf.item.extra()?))
-                })
-                .collect::<Vec<_>>();
-            let groups = set
-                .iter()
-                .filter_map(|inf| {
-                    if !inf.included {
-                        return None;
-                    }
-                    Some((inf.item.package(), inf.item.group()?))
-                })
-                .collect::<Vec<_>>();
-            // Notably, the marker must be possible to satisfy with the extras and groups alone.
-            // For example, when `a` and `b` conflict, this marker does not simplify:
-            // ```
-            // (platform_machine == 'x86_64' and extra == 'extra-5-foo-b') or extra == 'extra-5-foo-a'
-            // ````
-            graph[edge_index].evaluate_only_extras(&extras, &groups)
-        });
-        if all_paths_satisfied {
-            for set in inference_sets {
-                for inf in set {
-                    // TODO(konsti): Now that `Inference` is public, move more `included` handling
-                    // to `UniversalMarker`.
-                    if inf.included {
-                        graph[edge_index].assume_conflict_item(&inf.item);
-                    } else {
-                        graph[edge_index].assume_not_conflict_item(&inf.item);
-                    }
-                }
-            }
-        } else {
-            graph[edge_index].unify_inference_sets(inference_sets);
-        }
+    /// Finish the solver, returning the markers propagated so far.
+    pub(crate) fn into_map(self) -> FxHashMap<NodeIndex, Marker> {
+        self.reachability
     }
 }
 
-pub(crate) trait Reachable<T> {
-    /// The marker representing the "true" value.
-    fn true_marker() -> T;
-
-    /// The marker representing the "false" value.
-    fn false_marker() -> T;
+/// Like [`marker_reachability`], but starting from an arbitrary node: the marker for each node
+/// is the union of the markers over all paths from `from` to that node, e.g., to determine
+/// under which markers a package is an (in)direct dependency of another package.
+///
+/// `from` is treated as the sole root, with a `true` marker. Nodes that aren't reachable from
+/// `from` are absent from the map.
+#[allow(dead_code)]
+pub(crate) fn transitive_closure_markers<
+    Marker: Boolean + Copy + PartialEq,
+    Node,
+    Edge: Reachable<Marker>,
+>(
+    graph: &Graph<Node, Edge>,
+    from: NodeIndex,
+) -> FxHashMap<NodeIndex, Marker> {
+    let mut solver = ReachabilitySolver::from_seeds(graph, [(from, Edge::true_marker())]);
+    while solver.step().is_some() {}
+    solver.into_map()
+}
 
-    /// The marker attached to the edge.
-    fn marker(&self) -> T;
+/// Determine the Python versions under which each reachable node is actually installed, i.e.,
+/// the intersection of the global `requires-python` with the Python version constraints of the
+/// node's reachability marker.
+///
+/// This can be used to validate package metadata against the environments a package is
+/// installed in, e.g., to flag a package that requires Python 3.12 but is reachable under
+/// Python 3.8. Nodes that are unreachable (absent or `false` in `reachability`) are omitted.
+#[allow(dead_code)]
+pub(crate) fn effective_requires_python<Node, Edge>(
+    graph: &Graph<Node, Edge>,
+    reachability: &FxHashMap<NodeIndex, UniversalMarker>,
+    global: MarkerTree,
+) -> FxHashMap<NodeIndex, MarkerTree> {
+    graph
+        .node_indices()
+        .filter_map(|index| {
+            let marker = reachability.get(&index)?;
+            if marker.is_false() {
+                return None;
+            }
+            let mut python_versions = marker.requires_python_marker();
+            python_versions.and(global);
+            Some((index, python_versions))
+        })
+        .collect()
 }
 
-impl Reachable<Self> for MarkerTree {
-    fn true_marker() -> Self {
-        Self::TRUE
-    }
+/// Convert the [`marker_reachability`] of each node into the marker that is written to the
+/// lockfile.
+///
+/// Each marker is normalized the same way, so that the lockfile is deterministic and minimal:
+///
+/// 1. Python version bounds that are implied by `requires-python` are removed (see
+///    [`UniversalMarker::strip_redundant_python_bounds`]).
+/// 2. If there are any `fork_markers`, the marker is specialized to their union (see
+///    [`UniversalMarker::specialize`]), since the lockfile is only ever used within them.
+///
+/// Known `platform_system` aliases need no extra step, since markers already spell them as
+/// `sys_platform` by construction.
+///
+/// Like the removed Python version bounds, the result is only equivalent to the original marker
+/// within `requires-python` and the forks. Nodes that are unreachable (within the forks) are
+/// omitted.
+#[allow(dead_code)]
+pub(crate) fn reachability_to_lock_markers(
+    reachability: &FxHashMap<NodeIndex, UniversalMarker>,
+    requires_python: &RequiresPython,
+    fork_markers: &[UniversalMarker],
+) -> FxHashMap<NodeIndex, UniversalMarker> {
+    let forks =
+        (!fork_markers.is_empty()).then(|| root_markers::<UniversalMarker, _>(fork_markers));
 
-    fn false_marker() -> Self {
-        Self::FALSE
-    }
+    reachability
+        .iter()
+        .filter_map(|(index, marker)| {
+            let mut marker = *marker;
+            marker.strip_redundant_python_bounds(requires_python);
+            if let Some(forks) = forks {
+                marker.specialize(forks);
+            }
+            (!marker.is_false()).then_some((*index, marker))
+        })
+        .collect()
+}
 
-    fn marker(&self) -> Self {
-        *self
-    }
+/// Determine, for each node, the dependency group activation that is required for it to be
+/// reachable, e.g., to tell that a package is only installed when the `docs` group is active.
+///
+/// This is the group portion of the node's [`marker_reachability`] marker (see
+/// [`UniversalMarker::group_activation`]). Nodes that don't depend on the activation of any group
+/// have a `true` marker.
+#[allow(dead_code)]
+pub(crate) fn group_reachability<Node: GroupRoot>(
+    graph: &Graph<Node, UniversalMarker>,
+    fork_markers: &[UniversalMarker],
+) -> FxHashMap<NodeIndex, MarkerTree> {
+    let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+        marker_reachability(graph, fork_markers, &GroupSelection::all());
+    reachability
+        .into_iter()
+        .map(|(index, marker)| (index, marker.group_activation()))
+        .collect()
 }
 
-impl Reachable<Self> for UniversalMarker {
-    fn true_marker() -> Self {
-        Self::TRUE
-    }
+/// Compare recorded reachability markers (e.g., those of an existing lockfile) against freshly
+/// computed ones, e.g., to detect drift for `uv lock --check`.
+///
+/// Markers are compared semantically: a package only differs if its recorded marker doesn't
+/// imply its computed marker or vice versa. Packages that only appear in one of the maps are
+/// reported separately. The drifts are sorted by package.
+#[allow(dead_code)]
+pub(crate) fn reachability_matches<Id: Clone + Eq + Hash + Ord>(
+    recorded: &FxHashMap<Id, UniversalMarker>,
+    computed: &FxHashMap<Id, UniversalMarker>,
+) -> Result<(), Vec<MarkerDrift<Id>>> {
+    let implies =
+        |a: UniversalMarker, b: UniversalMarker| a.combined().is_disjoint(b.combined().negate());
 
-    fn false_marker() -> Self {
-        Self::FALSE
+    let mut drifts = Vec::new();
+    for (id, recorded_marker) in recorded {
+        match computed.get(id) {
+            Some(computed_marker) => {
+                if !implies(*recorded_marker, *computed_marker)
+                    || !implies(*computed_marker, *recorded_marker)
+                {
+                    drifts.push(MarkerDrift::Changed {
+                        id: id.clone(),
+                        recorded: *recorded_marker,
+                        computed: *computed_marker,
+                    });
+                }
+            }
+            None => drifts.push(MarkerDrift::OnlyRecorded(id.clone())),
+        }
+    }
+    for id in computed.keys() {
+        if !recorded.contains_key(id) {
+            drifts.push(MarkerDrift::OnlyComputed(id.clone()));
+        }
     }
 
-    fn marker(&self) -> Self {
-        *self
+    if drifts.is_empty() {
+        return Ok(());
     }
+    drifts.sort_by(|a, b| a.id().cmp(b.id()));
+    Err(drifts)
 }
 
-/// A trait for types that can be used as markers in the dependency graph.
-pub(crate) trait Boolean {
-    /// Perform a logical AND operation with another marker.
-    fn and(&mut self, other: Self);
-
-    /// Perform a logical OR operation with another marker.
-    fn or(&mut self, other: Self);
+/// A package whose recorded reachability marker doesn't match the computed one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MarkerDrift<Id> {
+    /// The package is reachable under different environments than recorded.
+    Changed {
+        id: Id,
+        recorded: UniversalMarker,
+        computed: UniversalMarker,
+    },
+    /// The package is only recorded, but no longer reachable.
+    OnlyRecorded(Id),
+    /// The package is reachable, but wasn't recorded.
+    OnlyComputed(Id),
 }
 
-impl Boolean for UniversalMarker {
-    fn and(&mut self, other: Self) {
-        self.and(other);
+impl<Id> MarkerDrift<Id> {
+    /// The package that drifted.
+    pub(crate) fn id(&self) -> &Id {
+        match self {
+            Self::Changed { id, .. } | Self::OnlyRecorded(id) | Self::OnlyComputed(id) => id,
+        }
     }
+}
 
-    fn or(&mut self, other: Self) {
-        self.or(other);
+/// Summarize how fragmented the given reachability markers are, e.g., to explain why a lock is
+/// large.
+#[allow(dead_code, clippy::cast_precision_loss)]
+pub(crate) fn reachability_stats(
+    reachability: &FxHashMap<NodeIndex, UniversalMarker>,
+) -> ReachabilityReport {
+    let mut report = ReachabilityReport::default();
+    // Markers are canonical, so semantically equal markers are also equal.
+    let mut distinct = FxHashSet::default();
+    let mut total_size = 0;
+    for marker in reachability.values() {
+        distinct.insert(*marker);
+        if marker.is_true() {
+            report.true_nodes += 1;
+        } else if marker.is_false() {
+            report.false_nodes += 1;
+        }
+        let size = marker.approx_size();
+        report.max_size = report.max_size.max(size);
+        total_size += size;
     }
+    report.distinct_markers = distinct.len();
+    if !reachability.is_empty() {
+        report.mean_size = total_size as f64 / reachability.len() as f64;
+    }
+    report
 }
 
-impl Boolean for MarkerTree {
-    fn and(&mut self, other: Self) {
-        self.and(other);
+/// A summary of the reachability markers of a graph, see [`reachability_stats`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct ReachabilityReport {
+    /// The number of distinct markers across all nodes.
+    pub(crate) distinct_markers: usize,
+    /// The number of nodes that are reachable in all environments.
+    pub(crate) true_nodes: usize,
+    /// The number of nodes that are unreachable.
+    pub(crate) false_nodes: usize,
+    /// The largest [`UniversalMarker::approx_size`] of any node's marker.
+    pub(crate) max_size: usize,
+    /// The mean [`UniversalMarker::approx_size`] of the nodes' markers.
+    pub(crate) mean_size: f64,
+}
+
+/// Returns a node filter that keeps exactly the nodes that are installed under the given
+/// environment with the given extras activated, according to `reachability`.
+///
+/// The filter is compatible with [`petgraph::visit::NodeFiltered`], such that petgraph
+/// algorithms can be run on the installed subgraph without mutating the graph. Nodes that are
+/// absent from `reachability` are filtered out. No dependency groups or projects are considered
+/// activated.
+#[allow(dead_code)]
+pub(crate) fn reachable_filter<'a>(
+    reachability: &'a FxHashMap<NodeIndex, UniversalMarker>,
+    env: &'a MarkerEnvironment,
+    extras: &'a [(PackageName, ExtraName)],
+) -> impl Fn(NodeIndex) -> bool + 'a {
+    move |index| {
+        reachability.get(&index).is_some_and(|marker| {
+            marker.evaluate(
+                env,
+                std::iter::empty::<&PackageName>(),
+                extras.iter().map(|(package, extra)| (package, extra)),
+                std::iter::empty::<(&PackageName, &GroupName)>(),
+            )
+        })
     }
+}
 
-    fn or(&mut self, other: Self) {
-        self.or(other);
+/// Determine, for each node, both the widest and the narrowest marker under which it is
+/// reachable, e.g., to tell packages that are always needed from those that are only needed
+/// conditionally.
+///
+/// The widest marker is the union of the markers of all paths to the node, as in
+/// [`marker_reachability`]. The narrowest marker is their intersection, i.e., the conditions
+/// under which every path to the node is followed. If the node is reachable through paths with
+/// disjoint conditions (e.g., one Linux-only and one Windows-only path), the narrowest marker is
+/// `false`, and so is the narrowest marker of every node that is only reached through it. Paths
+/// that can never be followed, e.g., through an edge whose marker is `false` or disjoint with the
+/// widest marker of its source, are ignored. So are the edges that close a cycle, i.e.,
+/// that lead from a node back to one of its ancestors: a path through such an edge has already
+/// reached the ancestor, so it can't narrow the ancestor's marker.
+#[allow(dead_code)]
+pub(crate) fn marker_reachability_bounds<Node: GroupRoot>(
+    graph: &Graph<Node, UniversalMarker>,
+    fork_markers: &[UniversalMarker],
+) -> FxHashMap<NodeIndex, (UniversalMarker, UniversalMarker)> {
+    let widest: FxHashMap<NodeIndex, UniversalMarker> =
+        marker_reachability(graph, fork_markers, &GroupSelection::all());
+
+    // An edge is on a path that can be followed if its marker intersects the widest marker of
+    // its source. Unlike the narrowest marker, which is `false` for a node reached through
+    // disjoint paths, the widest marker is only `false` if the node is never reached.
+    let is_satisfiable = |source: NodeIndex, edge: UniversalMarker| {
+        widest
+            .get(&source)
+            .is_some_and(|source| !source.is_disjoint(edge))
+    };
+
+    // Find the edges that close a cycle, following only the edges that can ever be traversed.
+    let mut back_edges = FxHashSet::default();
+    let traversable =
+        EdgeFiltered::from_fn(graph, |edge| is_satisfiable(edge.source(), *edge.weight()));
+    depth_first_search(&traversable, root_indices(graph), |event| {
+        if let DfsEvent::BackEdge(source, target) = event {
+            back_edges.extend(graph.edges_connecting(source, target).map(|edge| edge.id()));
+        }
+    });
+
+    // Like the fixpoint iteration of `marker_reachability`, but narrowing a node's marker with
+    // each path to it, and re-queuing the node whenever its marker narrowed. A node that is
+    // absent from `narrowest` hasn't been reached yet, while a `false` marker is propagated like
+    // any other: a path through a node that is reached through disjoint paths narrows its
+    // children to `false`, too.
+    let mut narrowest = FxHashMap::with_capacity_and_hasher(graph.node_count(), FxBuildHasher);
+    let mut queue = root_indices(graph);
+    let root_marker = root_markers(fork_markers);
+    for root_index in &queue {
+        narrowest.insert(*root_index, root_marker);
+    }
+    while let Some(parent_index) = queue.pop() {
+        let marker = narrowest[&parent_index];
+        for child_edge in graph.edges_directed(parent_index, Direction::Outgoing) {
+            if back_edges.contains(&child_edge.id()) {
+                continue;
+            }
+            // A path that can never be followed doesn't narrow the marker.
+            if !is_satisfiable(parent_index, *child_edge.weight()) {
+                continue;
+            }
+            let mut child_marker = *child_edge.weight();
+            child_marker.and(marker);
+            match narrowest.entry(child_edge.target()) {
+                Entry::Occupied(mut existing) => {
+                    child_marker.and(*existing.get());
+                    if &child_marker != existing.get() {
+                        existing.insert(child_marker);
+                        queue.push(child_edge.target());
+                    }
+                }
+                Entry::Vacant(vacant) => {
+                    vacant.insert(child_marker);
+                    queue.push(child_edge.target());
+                }
+            }
+        }
+    }
+
+    widest
+        .into_iter()
+        .map(|(index, widest)| {
+            let narrowest = narrowest
+                .get(&index)
+                .copied()
+                .unwrap_or(UniversalMarker::FALSE);
+            (index, (widest, narrowest))
+        })
+        .collect()
+}
+
+/// Like [`marker_reachability`], but with a compact representation for large graphs, in which
+/// most nodes share one of a few distinct markers.
+///
+/// Each distinct marker is stored once in the returned list, and each node maps to the index of
+/// its marker in that list. Markers are canonical, so semantically equal markers share an index.
+#[allow(dead_code)]
+pub(crate) fn marker_reachability_compact<Node: GroupRoot>(
+    graph: &Graph<Node, UniversalMarker>,
+    fork_markers: &[UniversalMarker],
+    group_selection: &GroupSelection,
+) -> (Vec<UniversalMarker>, FxHashMap<NodeIndex, u32>) {
+    let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+        marker_reachability(graph, fork_markers, group_selection);
+    let mut interner = MarkerInterner::default();
+    let indices = reachability
+        .into_iter()
+        .map(|(index, marker)| (index, interner.intern(marker).index()))
+        .collect();
+    (interner.into_markers(), indices)
+}
+
+/// The dependency groups whose roots are seeded when computing [`marker_reachability`], e.g.,
+/// as selected with `--group`, `--no-group` or `--only-group`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GroupSelection {
+    /// The groups to include. If empty, all groups are included.
+    include: Vec<GroupName>,
+    /// The groups to exclude, which takes precedence over `include` and `only`.
+    exclude: Vec<GroupName>,
+    /// The groups to include exclusively. If non-empty, only these groups are included, and
+    /// roots that don't belong to a group are excluded.
+    only: Vec<GroupName>,
+}
+
+impl GroupSelection {
+    /// A selection that includes every root.
+    pub(crate) fn all() -> Self {
+        Self::default()
+    }
+
+    /// Create a selection from the included, excluded and exclusively included groups.
+    #[allow(dead_code)]
+    pub(crate) fn new(
+        include: Vec<GroupName>,
+        exclude: Vec<GroupName>,
+        only: Vec<GroupName>,
+    ) -> Self {
+        Self {
+            include,
+            exclude,
+            only,
+        }
+    }
+
+    /// Returns `true` if the roots of the given group should be seeded.
+    pub(crate) fn contains(&self, group: &GroupName) -> bool {
+        if self.exclude.contains(group) {
+            return false;
+        }
+        if !self.only.is_empty() {
+            return self.only.contains(group);
+        }
+        self.include.is_empty() || self.include.contains(group)
+    }
+
+    /// Returns `true` if the roots that don't belong to any group should be seeded.
+    pub(crate) fn prod(&self) -> bool {
+        self.only.is_empty()
+    }
+}
+
+/// Like [`marker_reachability`], but gives up once the fixpoint has relaxed more than
+/// `max_relaxations` nodes.
+///
+/// Since markers only ever widen, the fixpoint always converges, so exceeding the budget
+/// indicates a bug. The budget is a safety valve against running unbounded in that case, e.g.,
+/// in CI. A relaxation is a single node being popped from the queue and its outgoing edges
+/// being updated.
+///
+/// The roots are seeded as in [`marker_reachability`], which runs without a budget.
+pub(crate) fn marker_reachability_bounded<
+    Marker: Boolean + Copy + PartialEq,
+    Node: GroupRoot,
+    Edge: Reachable<Marker>,
+>(
+    graph: &Graph<Node, Edge>,
+    fork_markers: &[Edge],
+    group_selection: &GroupSelection,
+    max_relaxations: Option<usize>,
+) -> Result<FxHashMap<NodeIndex, Marker>, ReachabilityNotConverged<Marker>> {
+    ReachabilitySolver::new(graph, fork_markers, group_selection).solve(max_relaxations)
+}
+
+/// The marker reachability fixpoint did not converge within its relaxation budget.
+#[derive(Debug)]
+pub(crate) struct ReachabilityNotConverged<Marker> {
+    /// The number of relaxations performed before giving up.
+    pub(crate) relaxations: usize,
+    /// The markers propagated so far, which may be narrower than the true reachability.
+    pub(crate) partial: FxHashMap<NodeIndex, Marker>,
+}
+
+/// Determine, for each node, the set of root nodes from which it is reachable.
+///
+/// A root counts as reaching a node if the marker of the paths from that root to the node is
+/// satisfiable. The roots are seeded as in [`marker_reachability`], and since reachability markers
+/// are the union over all paths, a node is present in the returned map if and only if its marker
+/// in [`marker_reachability`] is not `false`. Each reachable root is included in its own set.
+///
+/// The markers for all roots are propagated in a single pass: each node tracks the marker of the
+/// paths from each root separately, and is re-queued whenever the marker for any root widened.
+#[allow(dead_code)]
+pub(crate) fn reverse_reachability<
+    Marker: Boolean + Copy + PartialEq,
+    Node: GroupRoot,
+    Edge: Reachable<Marker>,
+>(
+    graph: &Graph<Node, Edge>,
+    fork_markers: &[Edge],
+    group_selection: &GroupSelection,
+) -> FxHashMap<NodeIndex, FxHashSet<NodeIndex>> {
+    let root_markers = root_markers(fork_markers);
+    let mut reachability: FxHashMap<NodeIndex, FxHashMap<NodeIndex, Marker>> =
+        FxHashMap::with_capacity_and_hasher(graph.node_count(), FxBuildHasher);
+    let mut queue = selected_root_indices(graph, group_selection);
+    for root_index in &queue {
+        reachability
+            .entry(*root_index)
+            .or_default()
+            .insert(*root_index, root_markers);
+    }
+
+    while let Some(parent_index) = queue.pop() {
+        let parent_markers = reachability[&parent_index].clone();
+        for child_edge in graph.edges_directed(parent_index, Direction::Outgoing) {
+            let child_markers = reachability.entry(child_edge.target()).or_default();
+            let mut widened = false;
+            for (root_index, marker) in &parent_markers {
+                let mut child_marker = child_edge.weight().marker();
+                child_marker.and(*marker);
+                match child_markers.entry(*root_index) {
+                    Entry::Occupied(mut existing) => {
+                        child_marker.or(*existing.get());
+                        if &child_marker != existing.get() {
+                            existing.insert(child_marker);
+                            widened = true;
+                        }
+                    }
+                    Entry::Vacant(vacant) => {
+                        vacant.insert(child_marker);
+                        widened = true;
+                    }
+                }
+            }
+            if widened {
+                queue.push(child_edge.target());
+            }
+        }
+    }
+
+    reachability
+        .into_iter()
+        .filter_map(|(node_index, markers)| {
+            let roots = markers
+                .into_iter()
+                .filter(|(_, marker)| !marker.is_false())
+                .map(|(root_index, _)| root_index)
+                .collect::<FxHashSet<_>>();
+            (!roots.is_empty()).then_some((node_index, roots))
+        })
+        .collect()
+}
+
+/// Assign each reachable node a layer for displaying the graph by depth.
+///
+/// The roots are in layer 0, and every other node is one layer below the deepest of its
+/// reachable predecessors. An edge is reachable if its source is reachable and the edge's marker
+/// intersects the source's reachability marker; unreachable edges and nodes are ignored, and
+/// nodes that aren't reachable are omitted from the returned map.
+///
+/// Nodes in a cycle don't have a well-defined depth, so all nodes in the same strongly connected
+/// component share a layer: one below the deepest reachable predecessor outside of the
+/// component, i.e., the layer at which the cycle is first entered.
+#[allow(dead_code)]
+pub(crate) fn reachability_layers<Marker: Boolean + Copy, Node, Edge: Reachable<Marker>>(
+    graph: &Graph<Node, Edge>,
+    reachability: &FxHashMap<NodeIndex, Marker>,
+) -> FxHashMap<NodeIndex, usize> {
+    let is_reachable_edge = |edge_index: EdgeIndex| {
+        let (source, _) = graph.edge_endpoints(edge_index).unwrap();
+        let Some(source_marker) = reachability.get(&source) else {
+            return false;
+        };
+        let mut marker = graph[edge_index].marker();
+        marker.and(*source_marker);
+        !marker.is_false()
+    };
+
+    // Since all nodes are retained, the node indices of the filtered graph match the original.
+    let reachable_graph = graph.filter_map(
+        |_, _| Some(()),
+        |edge_index, _| is_reachable_edge(edge_index).then_some(()),
+    );
+
+    // Note that `tarjan_scc` returns the components in reverse topological order.
+    let mut components = petgraph::algo::tarjan_scc(&reachable_graph);
+    components.reverse();
+
+    let mut layers = FxHashMap::with_capacity_and_hasher(graph.node_count(), FxBuildHasher);
+    for component in components {
+        let members = component.iter().copied().collect::<FxHashSet<_>>();
+        let layer = component
+            .iter()
+            .flat_map(|index| reachable_graph.edges_directed(*index, Direction::Incoming))
+            .filter(|edge| !members.contains(&edge.source()))
+            .filter_map(|edge| layers.get(&edge.source()))
+            .map(|layer| layer + 1)
+            .max()
+            .unwrap_or(0);
+        for index in component {
+            if reachability
+                .get(&index)
+                .is_some_and(|marker| !marker.is_false())
+            {
+                layers.insert(index, layer);
+            }
+        }
+    }
+    layers
+}
+
+/// The maximum number of paths returned by [`explain_reachability`].
+///
+/// The number of paths in a graph can grow exponentially with its depth, so the enumeration
+/// stops after this many paths.
+const MAX_EXPLAINED_PATHS: usize = 64;
+
+/// Explain why a node is reachable: each simple path of edges from a root to `node`, along with
+/// the conjunction of the markers along the path (starting from the union of the
+/// `fork_markers`), e.g., to answer `uv tree --why --markers`.
+///
+/// Paths whose marker is `false` are omitted, and no path visits a node twice. At most
+/// [`MAX_EXPLAINED_PATHS`] paths are returned, sorted by their edges.
+#[allow(dead_code)]
+pub(crate) fn explain_reachability<
+    Marker: Boolean + Copy + PartialEq,
+    Node,
+    Edge: Reachable<Marker>,
+>(
+    graph: &Graph<Node, Edge>,
+    node: NodeIndex,
+    fork_markers: &[Edge],
+) -> Vec<(Vec<EdgeIndex>, Marker)> {
+    /// Walk the incoming edges from `index` towards the roots.
+    ///
+    /// `path` holds the edges from `index` to the target node, and `marker` their conjunction.
+    fn walk<Marker: Boolean + Copy, Node, Edge: Reachable<Marker>>(
+        graph: &Graph<Node, Edge>,
+        index: NodeIndex,
+        root_marker: Marker,
+        path: &mut Vec<EdgeIndex>,
+        marker: Marker,
+        visited: &mut FxHashSet<NodeIndex>,
+        paths: &mut Vec<(Vec<EdgeIndex>, Marker)>,
+    ) {
+        let mut incoming = graph.edges_directed(index, Direction::Incoming).peekable();
+        if incoming.peek().is_none() {
+            let mut marker = marker;
+            marker.and(root_marker);
+            if !marker.is_false() {
+                paths.push((path.iter().rev().copied().collect(), marker));
+            }
+            return;
+        }
+        for edge in incoming {
+            if paths.len() >= MAX_EXPLAINED_PATHS {
+                return;
+            }
+            let mut marker = marker;
+            marker.and(edge.weight().marker());
+            if marker.is_false() || !visited.insert(edge.source()) {
+                continue;
+            }
+            path.push(edge.id());
+            walk(
+                graph,
+                edge.source(),
+                root_marker,
+                path,
+                marker,
+                visited,
+                paths,
+            );
+            path.pop();
+            visited.remove(&edge.source());
+        }
+    }
+
+    let root_marker = root_markers(fork_markers);
+    let mut paths = Vec::new();
+    let mut visited = FxHashSet::from_iter([node]);
+    walk(
+        graph,
+        node,
+        root_marker,
+        &mut Vec::new(),
+        Edge::true_marker(),
+        &mut visited,
+        &mut paths,
+    );
+    paths.sort_by(|(a, _), (b, _)| a.cmp(b));
+    paths
+}
+
+/// Find the shortest path of edges from `from` to `to`, along with the conjunction of the
+/// markers along the path, e.g., to explain why one package depends on another.
+///
+/// Among paths of the same length, a path whose marker is less restrictive (i.e., is implied by
+/// the marker of the other path) is preferred. Paths whose marker is `false` are never taken.
+/// Returns `None` if there is no such path.
+#[allow(dead_code)]
+pub(crate) fn shortest_marker_path<
+    Marker: Boolean + Copy + PartialEq,
+    Node,
+    Edge: Reachable<Marker>,
+>(
+    graph: &Graph<Node, Edge>,
+    from: NodeIndex,
+    to: NodeIndex,
+) -> Option<(Vec<EdgeIndex>, Marker)> {
+    // The best path found to each node, with the edge to its predecessor on that path.
+    let mut best: FxHashMap<NodeIndex, (Option<EdgeIndex>, Marker)> = FxHashMap::default();
+    best.insert(from, (None, Edge::true_marker()));
+
+    // A breadth-first search, one layer at a time, such that all paths of the same length to a
+    // node are compared before the node is expanded.
+    let mut layer = vec![from];
+    while !layer.is_empty() && !best.contains_key(&to) {
+        let mut next: Vec<NodeIndex> = Vec::new();
+        for parent_index in layer {
+            let parent_marker = best[&parent_index].1;
+            for edge in graph.edges_directed(parent_index, Direction::Outgoing) {
+                let mut marker = edge.weight().marker();
+                marker.and(parent_marker);
+                if marker.is_false() {
+                    continue;
+                }
+                let target = edge.target();
+                match best.entry(target) {
+                    Entry::Vacant(vacant) => {
+                        vacant.insert((Some(edge.id()), marker));
+                        next.push(target);
+                    }
+                    Entry::Occupied(mut existing) => {
+                        // Only paths of the same length are comparable.
+                        if !next.contains(&target) {
+                            continue;
+                        }
+                        let mut union = marker;
+                        union.or(existing.get().1);
+                        if union == marker && marker != existing.get().1 {
+                            existing.insert((Some(edge.id()), marker));
+                        }
+                    }
+                }
+            }
+        }
+        layer = next;
+    }
+
+    let (_, marker) = *best.get(&to)?;
+    let mut path = Vec::new();
+    let mut index = to;
+    while let Some(edge) = best[&index].0 {
+        path.push(edge);
+        index = graph.edge_endpoints(edge).unwrap().0;
+    }
+    path.reverse();
+    Some((path, marker))
+}
+
+/// Collect the root nodes of the graph.
+///
+/// Besides the actual virtual root node, virtual dev dependencies packages are also root
+/// nodes since the edges don't cover dev dependencies.
+fn root_indices<Node, Edge>(graph: &Graph<Node, Edge>) -> Vec<NodeIndex> {
+    graph
+        .node_indices()
+        .filter(|node_index| {
+            graph
+                .edges_directed(*node_index, Direction::Incoming)
+                .next()
+                .is_none()
+        })
+        .collect()
+}
+
+/// Collect the root nodes of the graph that are selected by `group_selection`, i.e., the nodes
+/// that are seeded by [`marker_reachability`].
+fn selected_root_indices<Node: GroupRoot, Edge>(
+    graph: &Graph<Node, Edge>,
+    group_selection: &GroupSelection,
+) -> Vec<NodeIndex> {
+    root_indices(graph)
+        .into_iter()
+        .filter(|root_index| match graph[*root_index].root_group() {
+            Some(group) => group_selection.contains(group),
+            None => group_selection.prod(),
+        })
+        .collect()
+}
+
+/// Returns the roots of the graph that belong to the given dependency group, e.g., to compute
+/// the reachability of a single group.
+///
+/// For `None`, this is the virtual root node. For a group, this is each virtual node for the
+/// group that is a root (see [`root_indices`]).
+#[allow(dead_code)]
+pub(crate) fn roots_for_group(
+    graph: &Graph<ResolutionGraphNode, UniversalMarker>,
+    group: Option<&GroupName>,
+) -> Vec<NodeIndex> {
+    root_indices(graph)
+        .into_iter()
+        .filter(|index| match group {
+            None => matches!(graph[*index], ResolutionGraphNode::Root),
+            Some(group) => graph[*index].group() == Some(group),
+        })
+        .collect()
+}
+
+/// The marker under which the root nodes are applicable.
+///
+/// The root nodes are always applicable, unless the user has restricted resolver
+/// environments with `tool.uv.environments`.
+fn root_markers<Marker: Boolean, Edge: Reachable<Marker>>(fork_markers: &[Edge]) -> Marker {
+    if fork_markers.is_empty() {
+        Edge::true_marker()
+    } else {
+        fork_markers
+            .iter()
+            .fold(Edge::false_marker(), |mut acc, edge| {
+                acc.or(edge.marker());
+                acc
+            })
+    }
+}
+
+/// Propagate the edge labels of a graph from the given roots, the same way [`marker_reachability`]
+/// propagates markers: the labels along a path are met, and the labels of all paths to a node
+/// are joined.
+///
+/// Each root starts with [`Lattice::top`]. Nodes without a path from any of the roots are absent
+/// from the returned map.
+#[allow(dead_code)]
+pub(crate) fn propagate_labels<Node, L: Lattice>(
+    graph: &Graph<Node, L>,
+    roots: &[NodeIndex],
+) -> FxHashMap<NodeIndex, L> {
+    let mut labels: FxHashMap<NodeIndex, L> =
+        FxHashMap::with_capacity_and_hasher(graph.node_count(), FxBuildHasher);
+    for root in roots {
+        labels.insert(*root, L::top());
+    }
+
+    let mut queue = roots.to_vec();
+    while let Some(parent_index) = queue.pop() {
+        let label = labels[&parent_index].clone();
+        for child_edge in graph.edges_directed(parent_index, Direction::Outgoing) {
+            // The label for all paths to the child through the parent.
+            let mut child_label = child_edge.weight().clone();
+            child_label.meet(&label);
+            let existing = labels.entry(child_edge.target()).or_insert_with(L::bottom);
+            // If the label is a subset of the existing label, joining wouldn't change the child.
+            if !child_label.is_subset(existing) {
+                existing.join(&child_label);
+                queue.push(child_edge.target());
+            }
+        }
+    }
+    labels
+}
+
+/// Imbibe the world knowledge about the given conflicts into every node and edge marker in the
+/// graph.
+///
+/// The marker encoding the mutual exclusions of the conflict sets is computed once and shared
+/// across all markers. Each marker is imbibed independently, so the result doesn't depend on the
+/// order of the nodes and edges. (The markers aren't imbibed in parallel: all marker operations
+/// go through the global marker interner, which is behind a lock.)
+///
+/// Returns the edges that can only be followed when the items of a conflict set are activated
+/// together, which the conflicts declare impossible. Imbibing doesn't remove such edges, but
+/// they indicate a contradictory configuration.
+pub(crate) fn imbibe_conflicts(
+    conflicts: &Conflicts,
+    graph: &mut Graph<ResolutionGraphNode, UniversalMarker>,
+) -> Vec<ContradictoryConflict> {
+    imbibe_conflicts_with(conflicts, &PairwiseConflictEncoder, graph)
+}
+
+/// Like [`imbibe_conflicts`], but with the given encoding of the conflicts into a conflict
+/// marker.
+pub(crate) fn imbibe_conflicts_with(
+    conflicts: &Conflicts,
+    encoder: &dyn ConflictEncoder,
+    graph: &mut Graph<ResolutionGraphNode, UniversalMarker>,
+) -> Vec<ContradictoryConflict> {
+    // Without any declared conflicts, there is nothing to imbibe. Skipping the markers is safe,
+    // since imbibing `true` leaves them unchanged, and their PEP 508 part is always kept in sync
+    // with the combined marker, so there is nothing to renormalize either.
+    if conflicts.is_empty() {
+        return Vec::new();
+    }
+
+    let conflict_marker = encoder.encode(conflicts);
+    // Encode each set on its own once, rather than once per contradicted edge.
+    let set_markers = conflicts
+        .iter()
+        .map(|set| {
+            let mut single = Conflicts::empty();
+            single.push(set.clone());
+            (
+                set,
+                UniversalMarker::new(MarkerTree::TRUE, encoder.encode(&single)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut contradictions = Vec::new();
+    for edge_index in graph.edge_indices() {
+        let marker = graph[edge_index];
+        if marker.is_false() {
+            continue;
+        }
+        // Only check the individual sets if the conflicts as a whole contradict the edge.
+        let mut allowed = marker;
+        allowed.and(UniversalMarker::new(MarkerTree::TRUE, conflict_marker));
+        if !allowed.is_false() {
+            continue;
+        }
+        let len = contradictions.len();
+        for (set, set_marker) in &set_markers {
+            let mut allowed = marker;
+            allowed.and(*set_marker);
+            if allowed.is_false() {
+                contradictions.push(ContradictoryConflict {
+                    edge: edge_index,
+                    sets: vec![(*set).clone()],
+                });
+            }
+        }
+        // If no set contradicts the edge on its own, only a combination of them does.
+        if contradictions.len() == len {
+            contradictions.push(ContradictoryConflict {
+                edge: edge_index,
+                sets: minimal_contradiction(conflicts, encoder, marker),
+            });
+        }
+    }
+
+    for node in graph.node_weights_mut() {
+        if let ResolutionGraphNode::Dist(dist) = node {
+            dist.marker.imbibe(conflict_marker);
+        }
+    }
+    for weight in graph.edge_weights_mut() {
+        weight.imbibe(conflict_marker);
+    }
+    contradictions
+}
+
+/// Find a minimal combination of the given conflicts that contradicts `marker`, i.e., one that
+/// no longer contradicts it once any of its sets is left out.
+///
+/// The conflicts as a whole must contradict `marker`.
+fn minimal_contradiction(
+    conflicts: &Conflicts,
+    encoder: &dyn ConflictEncoder,
+    marker: UniversalMarker,
+) -> Vec<ConflictSet> {
+    let contradicts = |sets: &[ConflictSet]| {
+        let mut combination = Conflicts::empty();
+        for set in sets {
+            combination.push(set.clone());
+        }
+        let mut allowed = marker;
+        allowed.and(UniversalMarker::new(
+            MarkerTree::TRUE,
+            encoder.encode(&combination),
+        ));
+        allowed.is_false()
+    };
+
+    // Leave out each set in turn, keeping it only if it's needed for the contradiction.
+    let mut sets = conflicts.iter().cloned().collect::<Vec<_>>();
+    let mut index = 0;
+    while index < sets.len() {
+        let set = sets.remove(index);
+        if !contradicts(&sets) {
+            sets.insert(index, set);
+            index += 1;
+        }
+    }
+    sets
+}
+
+/// An edge that can only be followed when conflicting items are activated together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ContradictoryConflict {
+    /// The contradicted edge.
+    pub(crate) edge: EdgeIndex,
+    /// The declared conflict sets that the edge's marker contradicts together.
+    ///
+    /// This is a single set, unless the edge only contradicts a combination of sets, in which
+    /// case it is a minimal such combination.
+    pub(crate) sets: Vec<ConflictSet>,
+}
+
+/// Check that the conflict marker of every edge only references items of the declared conflicts.
+///
+/// Conflict markers only arise from declared conflicts, so an edge whose conflict marker
+/// references any other extra, group or project indicates a bug in how the marker was
+/// constructed.
+#[allow(dead_code)]
+pub(crate) fn assert_conflict_markers_wellformed<Node>(
+    graph: &Graph<Node, UniversalMarker>,
+    conflicts: &Conflicts,
+) -> Result<(), Vec<IllFormedEdge>> {
+    let declared = conflicts
+        .iter()
+        .flat_map(ConflictSet::iter)
+        .collect::<FxHashSet<_>>();
+
+    let mut ill_formed = Vec::new();
+    for edge in graph.edge_references() {
+        let (include, exclude) = match edge.weight().conflict().filter_rules() {
+            Ok(rules) => rules,
+            Err(err) => {
+                ill_formed.push(IllFormedEdge::Invalid {
+                    edge: edge.id(),
+                    err,
+                });
+                continue;
+            }
+        };
+        let mut undeclared = include
+            .into_iter()
+            .chain(exclude)
+            .filter(|item| !declared.contains(item))
+            .collect::<Vec<_>>();
+        if undeclared.is_empty() {
+            continue;
+        }
+        undeclared.sort();
+        undeclared.dedup();
+        ill_formed.push(IllFormedEdge::Undeclared {
+            edge: edge.id(),
+            items: undeclared,
+        });
+    }
+
+    if ill_formed.is_empty() {
+        Ok(())
+    } else {
+        Err(ill_formed)
+    }
+}
+
+/// An edge whose conflict marker doesn't only reference items of the declared conflicts.
+#[derive(Debug)]
+pub(crate) enum IllFormedEdge {
+    /// The edge's conflict marker references items that aren't part of any conflict set.
+    Undeclared {
+        edge: EdgeIndex,
+        items: Vec<ConflictItem>,
+    },
+    /// The edge's conflict marker contains an extra that isn't an encoded conflict item.
+    Invalid { edge: EdgeIndex, err: ResolveError },
+}
+
+/// Traverse the given dependency graph and propagate activated markers.
+///
+/// For example, given an edge like `foo[x1] -> bar`, then it is known that
+/// `x1` is activated. This in turn can be used to simplify any downstream
+/// conflict markers with `extra == "x1"` in them (by replacing `extra == "x1"`
+/// with `true`).
+pub(crate) fn simplify_conflict_markers(
+    conflicts: &Conflicts,
+    graph: &mut Graph<ResolutionGraphNode, UniversalMarker>,
+) {
+    simplify_conflict_markers_returning_assumptions(conflicts, graph);
+}
+
+/// Like [`simplify_conflict_markers`], but returns the inferences that were assumed for each
+/// edge, e.g., to cache them for an incremental re-simplification that skips unchanged edges.
+///
+/// The map reflects the assumptions that were actually applied: an edge is only present if its
+/// conflict marker was simplified by assuming the given items to be activated (or not).
+pub(crate) fn simplify_conflict_markers_returning_assumptions(
+    conflicts: &Conflicts,
+    graph: &mut Graph<ResolutionGraphNode, UniversalMarker>,
+) -> FxHashMap<EdgeIndex, BTreeSet<Inference>> {
+    let mut assumptions: FxHashMap<EdgeIndex, BTreeSet<Inference>> = FxHashMap::default();
+
+    // Do nothing if there are no declared conflicts. Without any declared
+    // conflicts, we know we have no conflict markers and thus nothing to
+    // simplify by determining which extras are activated at different points
+    // in the dependency graph.
+    if conflicts.is_empty() {
+        return assumptions;
+    }
+
+    // The set of activated extras and groups for each node. Each set for a
+    // node already includes the extra or group activated by the node itself.
+    // The ROOT nodes don't have any extras/groups activated beyond their own.
+    let mut activated: FxHashMap<NodeIndex, Vec<FxHashSet<ConflictItem>>> = FxHashMap::default();
+
+    // Process the strongly connected components in topological order, so that
+    // the activated sets of every predecessor are final before we propagate
+    // them to a node's outgoing edges. Within a component (i.e., a cycle), we
+    // iterate until the activated sets reach a fixpoint. This terminates since
+    // the sets only ever grow.
+    //
+    // Visiting each node once its predecessors are final yields the same
+    // activated sets as re-visiting a node whenever its sets grow, but
+    // propagates each set along an acyclic edge only once.
+    //
+    // Note that `tarjan_scc` returns the components in reverse topological
+    // order.
+    let mut components = petgraph::algo::tarjan_scc(&*graph);
+    components.reverse();
+    for component in &components {
+        let members = component.iter().copied().collect::<FxHashSet<_>>();
+        for &index in component {
+            if graph
+                .edges_directed(index, Direction::Incoming)
+                .next()
+                .is_none()
+            {
+                let mut set = FxHashSet::default();
+                insert_activated_items(&graph[index], &mut set);
+                activated.insert(index, vec![set]);
+            }
+        }
+
+        loop {
+            let mut change = false;
+            for &parent_index in component {
+                // Nodes without any activated sets aren't reachable from a root.
+                let Some(sets) = activated.get(&parent_index).cloned() else {
+                    continue;
+                };
+                for child_edge in graph.edges_directed(parent_index, Direction::Outgoing) {
+                    let child_index = child_edge.target();
+                    for mut set in sets.iter().cloned() {
+                        insert_activated_items(&graph[child_index], &mut set);
+                        let existing = activated.entry(child_index).or_default();
+                        // This is doing a linear scan for testing membership, which
+                        // is non-ideal. But it's not actually clear that there's a
+                        // strictly better alternative without a real workload being
+                        // slow because of this. Namely, we are checking whether the
+                        // _set_ being inserted is equivalent to an existing set. So
+                        // instead of, say, `Vec<FxHashSet<ConflictItem>>`, we could
+                        // have `BTreeSet<BTreeSet<ConflictItem>>`. But this in turn
+                        // makes mutating the elements in each set (done above) more
+                        // difficult and likely require more allocations.
+                        //
+                        // So if this does result in a perf slowdown on some real
+                        // work-load, I think the first step would be to re-examine
+                        // whether we're doing more work than we need to be doing. If
+                        // we aren't, then we might want a more purpose-built data
+                        // structure for this.
+                        if !existing.contains(&set) {
+                            existing.push(set);
+                            // Only changes within the component require another
+                            // pass. Later components are processed afterwards.
+                            change |= members.contains(&child_index);
+                        }
+                    }
+                }
+            }
+            if !change {
+                break;
+            }
+        }
+    }
+
+    let mut inferences: FxHashMap<NodeIndex, Vec<BTreeSet<Inference>>> = FxHashMap::default();
+    for (node_id, sets) in activated {
+        let mut new_sets = Vec::with_capacity(sets.len());
+        for set in sets {
+            let mut new_set = BTreeSet::default();
+            for item in set {
+                for conflict_set in conflicts.iter() {
+                    if !conflict_set.contains(item.package(), item.as_ref().kind()) {
+                        continue;
+                    }
+                    for conflict_item in conflict_set.iter() {
+                        if conflict_item == &item {
+                            continue;
+                        }
+                        new_set.insert(Inference {
+                            item: conflict_item.clone(),
+                            included: false,
+                        });
+                    }
+                }
+                new_set.insert(Inference {
+                    item,
+                    included: true,
+                });
+            }
+            new_sets.push(new_set);
+        }
+        inferences.insert(node_id, new_sets);
+    }
+
+    for edge_index in (0..graph.edge_count()).map(EdgeIndex::new) {
+        let (from_index, to_index) = graph.edge_endpoints(edge_index).unwrap();
+        // If there are ambiguous edges (i.e., two or more edges
+        // with the same package name), then we specifically skip
+        // conflict marker simplification. It seems that in some
+        // cases, the logic encoded in `inferences` isn't quite enough
+        // to perfectly disambiguate between them. It's plausible we
+        // could do better here, but it requires smarter simplification
+        // logic. ---AG
+        let ambiguous_edges = graph
+            .edges_directed(from_index, Direction::Outgoing)
+            .filter(|edge| graph[to_index].package_name() == graph[edge.target()].package_name())
+            .count();
+        if ambiguous_edges > 1 {
+            continue;
+        }
+        let Some(inference_sets) = inferences.get(&from_index) else {
+            continue;
+        };
+        // If not all possible paths (represented by our inferences)
+        // satisfy the conflict marker on this edge, then we can't make any
+        // simplifications. Namely, because it follows that out inferences
+        // aren't always true. Some of them may sometimes be false.
+        let all_paths_satisfied = inference_sets.iter().all(|set| {
+            let extras = set
+                .iter()
+                .filter_map(|inf| {
+                    if !inf.included {
+                        return None;
+                    }
+                    Some((inf.item.package(), inf.item.extra()?))
+                })
+                .collect::<Vec<_>>();
+            let groups = set
+                .iter()
+                .filter_map(|inf| {
+                    if !inf.included {
+                        return None;
+                    }
+                    Some((inf.item.package(), inf.item.group()?))
+                })
+                .collect::<Vec<_>>();
+            // Notably, the marker must be possible to satisfy with the extras and groups alone.
+            // For example, when `a` and `b` conflict, this marker does not simplify:
+            // ```
+            // (platform_machine == 'x86_64' and extra == 'extra-5-foo-b') or extra == 'extra-5-foo-a'
+            // ````
+            graph[edge_index].evaluate_only_extras(&extras, &groups)
+        });
+        if all_paths_satisfied {
+            let mut assumed = BTreeSet::new();
+            for set in inference_sets {
+                for inf in set {
+                    // TODO(konsti): Now that `Inference` is public, move more `included` handling
+                    // to `UniversalMarker`.
+                    if inf.included {
+                        graph[edge_index].assume_conflict_item(&inf.item);
+                    } else {
+                        graph[edge_index].assume_not_conflict_item(&inf.item);
+                    }
+                    assumed.insert(inf.clone());
+                }
+            }
+            if !assumed.is_empty() {
+                assumptions.insert(edge_index, assumed);
+            }
+        } else {
+            graph[edge_index].unify_inference_sets(inference_sets);
+        }
+    }
+    assumptions
+}
+
+/// Inserts the extra or group activated by the given node, if any, into the
+/// given set of activated items.
+fn insert_activated_items(node: &ResolutionGraphNode, set: &mut FxHashSet<ConflictItem>) {
+    if let Some((package, extra)) = node.package_extra_names() {
+        set.insert(ConflictItem::from((package.clone(), extra.clone())));
+    }
+    if let Some((package, group)) = node.package_group_names() {
+        set.insert(ConflictItem::from((package.clone(), group.clone())));
+    }
+}
+
+/// Returns a package-level view of the edges of the resolution graph.
+///
+/// The virtual root node and the virtual extra and dependency group nodes are skipped. Instead,
+/// a path from a base package through one or more virtual nodes to another base package is
+/// collapsed into a direct edge, whose marker is the conjunction of the markers along the path.
+/// For example, `foo -> bar[x]` (with marker `a`) followed by `bar[x] -> baz` (with marker `b`)
+/// collapses into `foo -> baz` with marker `a and b`. If there are multiple such paths between
+/// the same two packages, their markers are combined with `or` into a single edge.
+///
+/// Edges out of the virtual root are dropped, since the root is not a package.
+///
+/// Packages are identified by their node index rather than by their lock [`PackageId`], since
+/// building a [`PackageId`] requires the workspace root and can fail; callers can map the
+/// indices through the graph as needed.
+///
+/// [`PackageId`]: crate::lock::PackageId
+#[allow(dead_code)]
+pub(crate) fn real_edges(
+    graph: &Graph<ResolutionGraphNode, UniversalMarker>,
+) -> impl Iterator<Item = (NodeIndex, NodeIndex, UniversalMarker)> + '_ {
+    let is_real = |index: NodeIndex| match &graph[index] {
+        ResolutionGraphNode::Root => false,
+        ResolutionGraphNode::Dist(dist) => dist.is_base(),
+    };
+
+    graph
+        .node_indices()
+        .filter(move |index| is_real(*index))
+        .flat_map(move |source| {
+            let mut edges: Vec<(NodeIndex, UniversalMarker)> = Vec::new();
+            let mut visited: FxHashMap<NodeIndex, UniversalMarker> = FxHashMap::default();
+            let mut stack = vec![(source, UniversalMarker::TRUE)];
+            while let Some((index, marker)) = stack.pop() {
+                for edge in graph.edges_directed(index, Direction::Outgoing) {
+                    let mut edge_marker = marker;
+                    edge_marker.and(*edge.weight());
+                    let target = edge.target();
+                    if target == source {
+                        continue;
+                    }
+                    if is_real(target) {
+                        if let Some((_, existing)) =
+                            edges.iter_mut().find(|(existing, _)| *existing == target)
+                        {
+                            existing.or(edge_marker);
+                        } else {
+                            edges.push((target, edge_marker));
+                        }
+                    } else {
+                        // A virtual node may be reached along several paths, so re-visit it
+                        // whenever its accumulated marker widens.
+                        match visited.entry(target) {
+                            Entry::Vacant(entry) => {
+                                entry.insert(edge_marker);
+                                stack.push((target, edge_marker));
+                            }
+                            Entry::Occupied(mut entry) => {
+                                let mut widened = *entry.get();
+                                widened.or(edge_marker);
+                                if widened != *entry.get() {
+                                    entry.insert(widened);
+                                    stack.push((target, widened));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            edges
+                .into_iter()
+                .map(move |(target, marker)| (source, target, marker))
+        })
+}
+
+/// Removes the virtual extra and dependency group nodes from the resolution graph, connecting
+/// the predecessors of each virtual node directly to its successors.
+///
+/// The marker of each contracted edge is the conjunction of the markers along the path. Since
+/// passing through a virtual node means that its extra or group is activated, that item is
+/// assumed to be activated in the markers of the edges out of the virtual node (as with
+/// [`UniversalMarker::assume_conflict_item`]). Parallel edges are combined by OR-ing their
+/// markers, and self-edges are dropped, since they don't affect reachability.
+///
+/// The virtual root is kept, since it seeds the reachability of the graph. As long as the edges
+/// into a virtual node are gated on its item (if the item is part of a conflict), the
+/// reachability of the remaining nodes is unchanged.
+///
+/// Note that removing nodes shifts the indices of other nodes, so any indices held by the
+/// caller may be invalidated.
+#[allow(dead_code)]
+pub(crate) fn contract_virtual_nodes(graph: &mut Graph<ResolutionGraphNode, UniversalMarker>) {
+    let is_virtual = |node: &ResolutionGraphNode| match node {
+        ResolutionGraphNode::Root => false,
+        ResolutionGraphNode::Dist(dist) => !dist.is_base(),
+    };
+
+    // Removing a node moves the last node into its slot. Iterating in reverse, the moved node
+    // was already visited (and kept), so no virtual node is skipped.
+    for index in graph.node_indices().rev().collect::<Vec<_>>() {
+        if !is_virtual(&graph[index]) {
+            continue;
+        }
+
+        let mut activated = FxHashSet::default();
+        insert_activated_items(&graph[index], &mut activated);
+        let incoming = graph
+            .edges_directed(index, Direction::Incoming)
+            .filter(|edge| edge.source() != index)
+            .map(|edge| (edge.source(), *edge.weight()))
+            .collect::<Vec<_>>();
+        let outgoing = graph
+            .edges_directed(index, Direction::Outgoing)
+            .filter(|edge| edge.target() != index)
+            .map(|edge| {
+                let mut marker = *edge.weight();
+                for item in &activated {
+                    marker.assume_conflict_item(item);
+                }
+                (edge.target(), marker)
+            })
+            .collect::<Vec<_>>();
+
+        for (source, incoming_marker) in &incoming {
+            for (target, outgoing_marker) in &outgoing {
+                if source == target {
+                    continue;
+                }
+                let mut marker = *incoming_marker;
+                marker.and(*outgoing_marker);
+                if marker.is_false() {
+                    continue;
+                }
+                add_or_merge_edge(graph, *source, *target, marker);
+            }
+        }
+        graph.remove_node(index);
+    }
+}
+
+/// Merges node `b` into node `a`, removing `b` from the graph.
+///
+/// Every edge incident to `b` is redirected onto `a`. If this results in an
+/// edge parallel to an existing one, the two are combined by OR-ing their
+/// markers. Edges between `a` and `b` would become self-edges, which don't
+/// affect reachability, so they are dropped.
+///
+/// Note that removing a node shifts the index of the last node in the graph
+/// into `b`'s slot, so any indices held by the caller other than `a` may be
+/// invalidated.
+#[allow(dead_code)]
+pub(crate) fn merge_nodes(
+    graph: &mut Graph<ResolutionGraphNode, UniversalMarker>,
+    a: NodeIndex,
+    b: NodeIndex,
+) {
+    debug_assert_ne!(a, b, "cannot merge a node into itself");
+
+    let incoming = graph
+        .edges_directed(b, Direction::Incoming)
+        .map(|edge| (edge.source(), *edge.weight()))
+        .collect::<Vec<_>>();
+    let outgoing = graph
+        .edges_directed(b, Direction::Outgoing)
+        .map(|edge| (edge.target(), *edge.weight()))
+        .collect::<Vec<_>>();
+
+    for (source, marker) in incoming {
+        if source == a || source == b {
+            continue;
+        }
+        add_or_merge_edge(graph, source, a, marker);
+    }
+    for (target, marker) in outgoing {
+        if target == a || target == b {
+            continue;
+        }
+        add_or_merge_edge(graph, a, target, marker);
+    }
+
+    graph.remove_node(b);
+}
+
+/// Adds an edge from `source` to `target`, OR-ing the marker into an existing
+/// edge between the two nodes if there is one.
+fn add_or_merge_edge(
+    graph: &mut Graph<ResolutionGraphNode, UniversalMarker>,
+    source: NodeIndex,
+    target: NodeIndex,
+    marker: UniversalMarker,
+) {
+    if let Some(edge) = graph.find_edge(source, target) {
+        graph[edge].or(marker);
+    } else {
+        graph.add_edge(source, target, marker);
+    }
+}
+
+/// Compute a fingerprint of the structure of the graph, e.g., to skip recomputing the
+/// reachability of an unchanged graph.
+///
+/// The fingerprint covers the identity of each node (its package name, version, extra or group
+/// and distribution) and each edge (its endpoints and marker). It is independent of the order in
+/// which nodes and edges were added, since both are hashed in sorted order, and stable across
+/// runs, since markers are hashed by their string representation rather than by their interned
+/// handle.
+#[allow(dead_code)]
+pub(crate) fn graph_fingerprint(graph: &Graph<ResolutionGraphNode, UniversalMarker>) -> u64 {
+    let node_key = |index: NodeIndex| match &graph[index] {
+        ResolutionGraphNode::Root => None,
+        ResolutionGraphNode::Dist(dist) => Some((
+            dist.name.to_string(),
+            dist.version.to_string(),
+            dist.extra.as_ref().map(ToString::to_string),
+            dist.group.as_ref().map(ToString::to_string),
+            dist.dist.to_string(),
+        )),
+    };
+
+    let mut nodes = graph.node_indices().map(node_key).collect::<Vec<_>>();
+    nodes.sort_unstable();
+    let mut edges = graph
+        .edge_references()
+        .map(|edge| {
+            (
+                node_key(edge.source()),
+                node_key(edge.target()),
+                edge.weight().combined().try_to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    edges.sort_unstable();
+
+    let mut hasher = CacheKeyHasher::new();
+    nodes.cache_key(&mut hasher);
+    edges.cache_key(&mut hasher);
+    hasher.finish()
+}
+
+pub(crate) trait Reachable<T> {
+    /// The marker representing the "true" value.
+    fn true_marker() -> T;
+
+    /// The marker representing the "false" value.
+    fn false_marker() -> T;
+
+    /// The marker attached to the edge.
+    ///
+    /// This is called every time the source of the edge is relaxed, so it should be cheap, e.g.,
+    /// a copy of an interned marker.
+    fn marker(&self) -> T;
+}
+
+impl Reachable<Self> for MarkerTree {
+    fn true_marker() -> Self {
+        Self::TRUE
+    }
+
+    fn false_marker() -> Self {
+        Self::FALSE
+    }
+
+    fn marker(&self) -> Self {
+        *self
+    }
+}
+
+impl Reachable<Self> for UniversalMarker {
+    fn true_marker() -> Self {
+        Self::TRUE
+    }
+
+    fn false_marker() -> Self {
+        Self::FALSE
+    }
+
+    fn marker(&self) -> Self {
+        *self
+    }
+}
+
+/// A trait for graph nodes that may be the root of a dependency group.
+pub(crate) trait GroupRoot {
+    /// The dependency group this node is a root for, if any.
+    fn root_group(&self) -> Option<&GroupName>;
+}
+
+impl<T: GroupRoot + ?Sized> GroupRoot for &T {
+    fn root_group(&self) -> Option<&GroupName> {
+        (**self).root_group()
+    }
+}
+
+impl GroupRoot for ResolutionGraphNode {
+    fn root_group(&self) -> Option<&GroupName> {
+        self.group()
+    }
+}
+
+/// A trait for types that can be used as markers in the dependency graph.
+pub(crate) trait Boolean {
+    /// Perform a logical AND operation with another marker.
+    fn and(&mut self, other: Self);
+
+    /// Perform a logical OR operation with another marker.
+    fn or(&mut self, other: Self);
+
+    /// Returns `true` if the marker can never be satisfied.
+    fn is_false(&self) -> bool;
+
+    /// Returns `true` if the marker is always satisfied.
+    fn is_true(&self) -> bool;
+}
+
+impl Boolean for UniversalMarker {
+    fn and(&mut self, other: Self) {
+        self.and(other);
+    }
+
+    fn or(&mut self, other: Self) {
+        self.or(other);
+    }
+
+    fn is_false(&self) -> bool {
+        UniversalMarker::is_false(*self)
+    }
+
+    fn is_true(&self) -> bool {
+        UniversalMarker::is_true(*self)
+    }
+}
+
+impl Boolean for MarkerTree {
+    fn and(&mut self, other: Self) {
+        self.and(other);
+    }
+
+    fn or(&mut self, other: Self) {
+        self.or(other);
+    }
+
+    fn is_false(&self) -> bool {
+        MarkerTree::is_false(*self)
+    }
+
+    fn is_true(&self) -> bool {
+        MarkerTree::is_true(*self)
+    }
+}
+
+/// A bounded lattice of labels that can be propagated through a graph with [`propagate_labels`].
+pub(crate) trait Lattice: Clone {
+    /// The least element, i.e., the label of a node that isn't reachable.
+    fn bottom() -> Self;
+
+    /// The greatest element, i.e., the label of a root.
+    fn top() -> Self;
+
+    /// Replace this label with the least upper bound of it and `other`.
+    fn join(&mut self, other: &Self);
+
+    /// Replace this label with the greatest lower bound of it and `other`.
+    fn meet(&mut self, other: &Self);
+
+    /// Returns `true` if this label is less than or equal to `other`.
+    fn is_subset(&self, other: &Self) -> bool;
+}
+
+impl Lattice for UniversalMarker {
+    fn bottom() -> Self {
+        Self::FALSE
+    }
+
+    fn top() -> Self {
+        Self::TRUE
+    }
+
+    fn join(&mut self, other: &Self) {
+        self.or(*other);
+    }
+
+    fn meet(&mut self, other: &Self) {
+        self.and(*other);
+    }
+
+    fn is_subset(&self, other: &Self) -> bool {
+        // A ⊆ B exactly if A ∪ B = B.
+        let mut union = *self;
+        union.or(*other);
+        union == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use petgraph::visit::{Dfs, NodeFiltered};
+
+    use uv_distribution_types::{Dist, ResolvedDist};
+    use uv_pep440::Version;
+    use uv_pep508::{MarkerEnvironmentBuilder, VerbatimUrl};
+    use uv_pypi_types::{HashDigests, ParsedUrl, VerbatimParsedUrl};
+    use uv_redacted::DisplaySafeUrl;
+
+    use crate::resolution::AnnotatedDist;
+
+    use super::*;
+
+    /// Shortcut for creating a universal marker from a PEP 508 marker string.
+    fn marker(s: &str) -> UniversalMarker {
+        UniversalMarker::from_combined(MarkerTree::from_str(s).unwrap())
+    }
+
+    /// Shortcut for creating a distribution node with an optional extra.
+    fn dist_node(name: &str, extra: Option<&str>) -> ResolutionGraphNode {
+        let name = PackageName::from_str(name).unwrap();
+        let url = DisplaySafeUrl::parse(&format!("https://example.com/{name}-1.0.tar.gz")).unwrap();
+        let url = VerbatimParsedUrl {
+            parsed_url: ParsedUrl::try_from(url.clone()).unwrap(),
+            verbatim: VerbatimUrl::from_url(url),
+        };
+        let dist = Dist::from_url(name.clone(), url).unwrap();
+        ResolutionGraphNode::Dist(AnnotatedDist {
+            dist: ResolvedDist::Installable {
+                dist: Arc::new(dist),
+                version: Some(Version::new([1, 0])),
+            },
+            name,
+            version: Version::new([1, 0]),
+            extra: extra.map(|extra| ExtraName::from_str(extra).unwrap()),
+            group: None,
+            hashes: HashDigests::empty(),
+            metadata: None,
+            marker: UniversalMarker::TRUE,
+        })
+    }
+
+    /// Shortcut for creating a distribution node that is the root of a dependency group.
+    fn group_node(name: &str, group: &str) -> ResolutionGraphNode {
+        let mut node = dist_node(name, None);
+        if let ResolutionGraphNode::Dist(dist) = &mut node {
+            dist.group = Some(GroupName::from_str(group).unwrap());
+        }
+        node
+    }
+
+    /// Returns the reachability of the base package nodes, keyed by package name.
+    ///
+    /// Unlike node indices, package names are stable across rewrites of the graph, such as
+    /// merging or contracting nodes.
+    fn reachability_by_name(
+        graph: &Graph<ResolutionGraphNode, UniversalMarker>,
+    ) -> FxHashMap<PackageName, UniversalMarker> {
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(graph, &[], &GroupSelection::all());
+        let mut by_name: FxHashMap<PackageName, UniversalMarker> = FxHashMap::default();
+        let base = reachability
+            .into_iter()
+            .filter_map(|(index, marker)| match &graph[index] {
+                ResolutionGraphNode::Dist(dist) if dist.is_base() => Some((&dist.name, marker)),
+                _ => None,
+            });
+        for (name, marker) in base {
+            by_name
+                .entry(name.clone())
+                .and_modify(|existing| existing.or(marker))
+                .or_insert(marker);
+        }
+        by_name
+    }
+
+    /// Test graphs with plain string nodes don't have any dependency groups.
+    impl GroupRoot for str {
+        fn root_group(&self) -> Option<&GroupName> {
+            None
+        }
+    }
+
+    /// An edge that is only reachable through conflicting extras is simplified once the
+    /// activated extras from _every_ path have been propagated to it.
+    #[test]
+    fn simplify_conflict_markers_diamond() {
+        let foo = PackageName::from_str("foo").unwrap();
+        let x1 = ExtraName::from_str("x1").unwrap();
+        let x2 = ExtraName::from_str("x2").unwrap();
+
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ConflictSet::pair(
+            ConflictItem::from((foo.clone(), x1.clone())),
+            ConflictItem::from((foo.clone(), x2.clone())),
+        ));
+
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let foo_x1 = graph.add_node(dist_node("foo", Some("x1")));
+        let foo_x2 = graph.add_node(dist_node("foo", Some("x2")));
+        let bar = graph.add_node(dist_node("bar", None));
+        let baz = graph.add_node(dist_node("baz", None));
+        graph.add_edge(root, foo_x1, UniversalMarker::TRUE);
+        graph.add_edge(root, foo_x2, UniversalMarker::TRUE);
+        graph.add_edge(foo_x1, bar, UniversalMarker::TRUE);
+        graph.add_edge(foo_x2, bar, UniversalMarker::TRUE);
+        let edge = graph.add_edge(
+            bar,
+            baz,
+            UniversalMarker::new(
+                MarkerTree::TRUE,
+                ConflictMarker::extra(&foo, &x1).or(ConflictMarker::extra(&foo, &x2)),
+            ),
+        );
+
+        simplify_conflict_markers(&conflicts, &mut graph);
+        assert_eq!(graph[edge], UniversalMarker::TRUE);
+    }
+
+    /// A package that is only pulled in by a dev root is attributed to that root alone.
+    #[test]
+    fn reverse_reachability_dev_root() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let dev = graph.add_node("dev");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let pytest = graph.add_node("pytest");
+        let unreachable = graph.add_node("unreachable");
+        graph.add_edge(root, a, UniversalMarker::TRUE);
+        graph.add_edge(a, b, marker("sys_platform == 'linux'"));
+        graph.add_edge(dev, pytest, UniversalMarker::TRUE);
+        graph.add_edge(dev, b, UniversalMarker::TRUE);
+        graph.add_edge(root, unreachable, UniversalMarker::FALSE);
+
+        let provenance = reverse_reachability(&graph, &[], &GroupSelection::all());
+        assert_eq!(provenance[&root], FxHashSet::from_iter([root]));
+        assert_eq!(provenance[&dev], FxHashSet::from_iter([dev]));
+        assert_eq!(provenance[&a], FxHashSet::from_iter([root]));
+        assert_eq!(provenance[&b], FxHashSet::from_iter([root, dev]));
+        assert_eq!(provenance[&pytest], FxHashSet::from_iter([dev]));
+        assert!(!provenance.contains_key(&unreachable));
+
+        // A node has provenance if and only if it is reachable.
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        for node in graph.node_indices() {
+            assert_eq!(
+                provenance.contains_key(&node),
+                reachability
+                    .get(&node)
+                    .is_some_and(|marker| !marker.is_false()),
+            );
+        }
+    }
+
+    /// The fingerprint doesn't depend on the order in which the graph was built, but changes
+    /// with the markers.
+    #[test]
+    fn graph_fingerprint() {
+        let build = |reverse: bool, marker_b: UniversalMarker| {
+            let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+            let mut names = vec!["a", "b", "c"];
+            if reverse {
+                names.reverse();
+            }
+            let root = graph.add_node(ResolutionGraphNode::Root);
+            let mut nodes = FxHashMap::default();
+            for name in names {
+                nodes.insert(name, graph.add_node(dist_node(name, None)));
+            }
+            let mut edges = vec![
+                (root, nodes["a"], UniversalMarker::TRUE),
+                (nodes["a"], nodes["b"], marker_b),
+                (nodes["b"], nodes["c"], marker("python_version >= '3.10'")),
+            ];
+            if reverse {
+                edges.reverse();
+            }
+            for (source, target, marker) in edges {
+                graph.add_edge(source, target, marker);
+            }
+            graph
+        };
+
+        let linux = marker("sys_platform == 'linux'");
+        let fingerprint = super::graph_fingerprint(&build(false, linux));
+        assert_eq!(fingerprint, super::graph_fingerprint(&build(true, linux)));
+        assert_ne!(
+            fingerprint,
+            super::graph_fingerprint(&build(false, marker("sys_platform == 'darwin'")))
+        );
+    }
+
+    /// A minimal pseudo-random number generator (xorshift), so that the randomized tests below
+    /// are reproducible.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Returns a number in `0..n`.
+        fn below(&mut self, n: usize) -> usize {
+            usize::try_from(self.next() % n as u64).unwrap()
+        }
+    }
+
+    /// A brute-force reference for `marker_reachability`: relax every edge until nothing changes.
+    fn reference_reachability(
+        graph: &Graph<&str, UniversalMarker>,
+        fork_markers: &[UniversalMarker],
+    ) -> FxHashMap<NodeIndex, UniversalMarker> {
+        let root_marker = if fork_markers.is_empty() {
+            UniversalMarker::TRUE
+        } else {
+            fork_markers
+                .iter()
+                .fold(UniversalMarker::FALSE, |mut acc, fork| {
+                    acc.or(*fork);
+                    acc
+                })
+        };
+        let mut reachability: FxHashMap<NodeIndex, UniversalMarker> = graph
+            .node_indices()
+            .filter(|index| {
+                graph
+                    .edges_directed(*index, Direction::Incoming)
+                    .next()
+                    .is_none()
+            })
+            .map(|index| (index, root_marker))
+            .collect();
+        loop {
+            let mut changed = false;
+            for edge in graph.edge_references() {
+                let Some(mut marker) = reachability.get(&edge.source()).copied() else {
+                    continue;
+                };
+                marker.and(*edge.weight());
+                match reachability.entry(edge.target()) {
+                    Entry::Occupied(mut existing) => {
+                        let mut union = *existing.get();
+                        union.or(marker);
+                        if union != *existing.get() {
+                            existing.insert(union);
+                            changed = true;
+                        }
+                    }
+                    Entry::Vacant(vacant) => {
+                        vacant.insert(marker);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return reachability;
+            }
+        }
+    }
+
+    /// On random graphs with random markers (including cycles and conflict markers), the
+    /// optimized reachability variants agree with the brute-force reference.
+    #[test]
+    fn marker_reachability_matches_reference() {
+        const ITERATIONS: usize = 200;
+
+        let foo = PackageName::from_str("foo").unwrap();
+        let mut atoms = vec![UniversalMarker::TRUE, UniversalMarker::FALSE];
+        for platform in ["linux", "darwin", "win32"] {
+            atoms.push(marker(&format!("sys_platform == '{platform}'")));
+        }
+        for version in ["3.9", "3.10", "3.12"] {
+            atoms.push(marker(&format!("python_version >= '{version}'")));
+        }
+        for extra in ["x1", "x2"] {
+            let extra = ExtraName::from_str(extra).unwrap();
+            let conflict = ConflictMarker::extra(&foo, &extra);
+            atoms.push(UniversalMarker::new(MarkerTree::TRUE, conflict));
+            atoms.push(UniversalMarker::new(MarkerTree::TRUE, conflict.negate()));
+        }
+
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let random_marker = |rng: &mut XorShift| {
+            let mut marker = atoms[rng.below(atoms.len())];
+            match rng.below(3) {
+                0 => marker.and(atoms[rng.below(atoms.len())]),
+                1 => marker.or(atoms[rng.below(atoms.len())]),
+                _ => {}
+            }
+            marker
+        };
+
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        for _ in 0..ITERATIONS {
+            let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+            let nodes = names[..2 + rng.below(names.len() - 1)]
+                .iter()
+                .map(|name| graph.add_node(*name))
+                .collect::<Vec<_>>();
+            for _ in 0..rng.below(2 * nodes.len()) {
+                let source = nodes[rng.below(nodes.len())];
+                // Never add edges into the first node, so that there's always a root.
+                let target = nodes[1 + rng.below(nodes.len() - 1)];
+                let weight = random_marker(&mut rng);
+                graph.add_edge(source, target, weight);
+            }
+            let fork_markers = (0..rng.below(3))
+                .map(|_| random_marker(&mut rng))
+                .collect::<Vec<_>>();
+
+            let expected = reference_reachability(&graph, &fork_markers);
+            let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+                marker_reachability(&graph, &fork_markers, &GroupSelection::all());
+            assert_eq!(
+                reachability, expected,
+                "{graph:?} with forks {fork_markers:?}"
+            );
+            let bounded =
+                marker_reachability_bounded(&graph, &fork_markers, &GroupSelection::all(), None)
+                    .unwrap();
+            assert_eq!(bounded, expected, "{graph:?} with forks {fork_markers:?}");
+            if fork_markers.is_empty() {
+                let labels = propagate_labels(&graph, &root_indices(&graph));
+                assert_eq!(labels, expected, "{graph:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn roots_for_group() {
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let project = graph.add_node(dist_node("project", None));
+        let docs = graph.add_node(group_node("project", "docs"));
+        let dev = graph.add_node(group_node("project", "dev"));
+        let sphinx = graph.add_node(dist_node("sphinx", None));
+        graph.add_edge(root, project, UniversalMarker::TRUE);
+        graph.add_edge(docs, sphinx, UniversalMarker::TRUE);
+        graph.add_edge(dev, project, UniversalMarker::TRUE);
+
+        let docs_name = GroupName::from_str("docs").unwrap();
+        assert_eq!(super::roots_for_group(&graph, None), vec![root]);
+        assert_eq!(super::roots_for_group(&graph, Some(&docs_name)), vec![docs]);
+        let missing = GroupName::from_str("missing").unwrap();
+        assert!(super::roots_for_group(&graph, Some(&missing)).is_empty());
+    }
+
+    /// A root seeded with `false` leaves its exclusive subtree unreachable.
+    #[test]
+    fn reachability_solver_from_seeds() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let dev = graph.add_node("dev");
+        let shared = graph.add_node("shared");
+        let pytest = graph.add_node("pytest");
+        graph.add_edge(root, shared, UniversalMarker::TRUE);
+        graph.add_edge(dev, shared, UniversalMarker::TRUE);
+        graph.add_edge(dev, pytest, UniversalMarker::TRUE);
+
+        let solve = |seeds: [(NodeIndex, UniversalMarker); 2]| {
+            let mut solver = ReachabilitySolver::from_seeds(&graph, seeds);
+            while solver.step().is_some() {}
+            solver.into_map()
+        };
+
+        let linux = marker("sys_platform == 'linux'");
+        let reachability = solve([(root, linux), (dev, UniversalMarker::FALSE)]);
+        assert_eq!(reachability[&shared], linux);
+        assert!(
+            reachability
+                .get(&pytest)
+                .is_none_or(|marker| marker.is_false())
+        );
+
+        // Seeding the group root with a restriction only restricts its subtree.
+        let py312 = marker("python_version >= '3.12'");
+        let reachability = solve([(root, linux), (dev, py312)]);
+        assert_eq!(reachability[&pytest], py312);
+        let mut either = linux;
+        either.or(py312);
+        assert_eq!(reachability[&shared], either);
+    }
+
+    /// The lock markers drop a bound that `requires-python` implies and drop what the forks
+    /// imply. Platform aliases come out as `sys_platform`, since that is their canonical form.
+    #[test]
+    fn reachability_to_lock_markers() {
+        let requires_python =
+            RequiresPython::greater_than_equal_version(&Version::from_str("3.9").unwrap());
+        let a = NodeIndex::new(0);
+        let b = NodeIndex::new(1);
+        let unreachable = NodeIndex::new(2);
+        let mut reachability = FxHashMap::default();
+        reachability.insert(
+            a,
+            marker("python_version >= '3.9' and platform_system == 'Darwin'"),
+        );
+        reachability.insert(
+            b,
+            marker(
+                "(python_version >= '3.12' and platform_system == 'Linux') \
+                 or sys_platform == 'darwin'",
+            ),
+        );
+        reachability.insert(unreachable, UniversalMarker::FALSE);
+
+        let lock_markers =
+            super::reachability_to_lock_markers(&reachability, &requires_python, &[]);
+        assert_eq!(lock_markers[&a], marker("sys_platform == 'darwin'"));
+        assert_eq!(
+            lock_markers[&b],
+            marker(
+                "(python_full_version >= '3.12' and sys_platform == 'linux') or sys_platform == 'darwin'"
+            )
+        );
+        assert!(!lock_markers.contains_key(&unreachable));
+
+        // Within a Linux-only fork (spelled with the alias, which is canonical by construction),
+        // `b` only depends on the Python version.
+        let forks = [marker("platform_system == 'Linux'")];
+        let lock_markers =
+            super::reachability_to_lock_markers(&reachability, &requires_python, &forks);
+        assert_eq!(lock_markers[&b], marker("python_full_version >= '3.12'"));
+        assert!(!lock_markers.contains_key(&a));
+    }
+
+    /// Propagating markers as generic labels matches `marker_reachability`.
+    #[test]
+    fn propagate_labels_matches_marker_reachability() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let dev = graph.add_node("dev");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let unreachable = graph.add_node("unreachable");
+        graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        graph.add_edge(root, b, marker("sys_platform == 'darwin'"));
+        graph.add_edge(a, c, marker("python_version >= '3.10'"));
+        graph.add_edge(b, c, UniversalMarker::TRUE);
+        graph.add_edge(c, a, marker("implementation_name == 'pypy'"));
+        graph.add_edge(dev, b, marker("os_name == 'nt'"));
+        graph.add_edge(root, unreachable, UniversalMarker::FALSE);
+
+        let labels = propagate_labels(&graph, &[root, dev]);
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        assert_eq!(labels, reachability);
+    }
+
+    /// A well-behaved graph converges well within a budget proportional to its size, while a
+    /// graph that forces repeated widening of the same node exhausts a tight budget.
+    #[test]
+    fn marker_reachability_budget() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        graph.add_edge(root, a, UniversalMarker::TRUE);
+        graph.add_edge(a, b, marker("sys_platform == 'linux'"));
+        graph.add_edge(b, c, marker("python_version >= '3.10'"));
+
+        let bounded = marker_reachability_bounded(
+            &graph,
+            &[],
+            &GroupSelection::all(),
+            Some(2 * graph.node_count()),
+        )
+        .expect("a linear chain should converge");
+        let unbounded: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        assert_eq!(bounded, unbounded);
+
+        // Every platform-specific path to `sink` widens its marker, so `sink` is relaxed once
+        // per path.
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let sink = graph.add_node("sink");
+        for platform in ["linux", "darwin", "win32", "emscripten"] {
+            let node = graph.add_node(platform);
+            graph.add_edge(root, node, marker(&format!("sys_platform == '{platform}'")));
+            graph.add_edge(node, sink, UniversalMarker::TRUE);
+        }
+
+        let err = marker_reachability_bounded(
+            &graph,
+            &[],
+            &GroupSelection::all(),
+            Some(graph.node_count()),
+        )
+        .expect_err("repeated widening should exhaust the budget");
+        assert_eq!(err.relaxations, graph.node_count());
+        assert!(err.partial.contains_key(&sink));
+    }
+
+    /// Merging two nodes for the same package preserves the reachability of every other node,
+    /// and the merged node is reachable wherever either of the original nodes was.
+    #[test]
+    fn merge_nodes_preserves_reachability() {
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let a = graph.add_node(dist_node("foo", None));
+        let b = graph.add_node(dist_node("foo", None));
+        let bar = graph.add_node(dist_node("bar", None));
+        let baz = graph.add_node(dist_node("baz", None));
+        graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        graph.add_edge(root, b, marker("sys_platform == 'darwin'"));
+        graph.add_edge(a, b, UniversalMarker::TRUE);
+        graph.add_edge(a, bar, UniversalMarker::TRUE);
+        graph.add_edge(b, bar, marker("python_version >= '3.10'"));
+        graph.add_edge(b, baz, UniversalMarker::TRUE);
+
+        let before = reachability_by_name(&graph);
+        merge_nodes(&mut graph, a, b);
+        let after = reachability_by_name(&graph);
+
+        assert_eq!(graph.node_count(), 4);
+        assert!(graph.find_edge(a, a).is_none());
+        assert_eq!(before, after);
+        assert_eq!(
+            after[&PackageName::from_str("foo").unwrap()],
+            marker("sys_platform == 'linux' or sys_platform == 'darwin'")
+        );
+    }
+
+    /// With `--only-group test`, reachability is as if the `test` group root were the only root.
+    #[test]
+    fn marker_reachability_only_group() {
+        let add_test_group = |graph: &mut Graph<ResolutionGraphNode, UniversalMarker>| {
+            let test = graph.add_node(group_node("project", "test"));
+            let pytest = graph.add_node(dist_node("pytest", None));
+            let shared = graph.add_node(dist_node("shared", None));
+            graph.add_edge(test, pytest, marker("sys_platform == 'linux'"));
+            graph.add_edge(pytest, shared, UniversalMarker::TRUE);
+        };
+        let by_name = |graph: &Graph<ResolutionGraphNode, UniversalMarker>,
+                       group_selection: &GroupSelection| {
+            let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+                marker_reachability(graph, &[], group_selection);
+            reachability
+                .into_iter()
+                .filter_map(|(index, marker)| Some((graph[index].package_name()?.clone(), marker)))
+                .collect::<FxHashMap<PackageName, UniversalMarker>>()
+        };
+
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let project = graph.add_node(dist_node("project", None));
+        let requests = graph.add_node(dist_node("requests", None));
+        graph.add_edge(root, project, UniversalMarker::TRUE);
+        graph.add_edge(project, requests, UniversalMarker::TRUE);
+        add_test_group(&mut graph);
+        let lint = graph.add_node(group_node("project", "lint"));
+        let ruff = graph.add_node(dist_node("ruff", None));
+        graph.add_edge(lint, ruff, UniversalMarker::TRUE);
+
+        let mut only_test: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        add_test_group(&mut only_test);
+
+        let test = GroupName::from_str("test").unwrap();
+        let selection = GroupSelection::new(vec![], vec![], vec![test]);
+        let reachability = by_name(&graph, &selection);
+        assert_eq!(reachability, by_name(&only_test, &GroupSelection::all()));
+        assert!(!reachability.contains_key(&PackageName::from_str("requests").unwrap()));
+        assert!(!reachability.contains_key(&PackageName::from_str("ruff").unwrap()));
+
+        // Excluding a group skips its root, but keeps the non-group roots.
+        let lint = GroupName::from_str("lint").unwrap();
+        let selection = GroupSelection::new(vec![], vec![lint], vec![]);
+        let reachability = by_name(&graph, &selection);
+        assert!(reachability.contains_key(&PackageName::from_str("requests").unwrap()));
+        assert!(reachability.contains_key(&PackageName::from_str("pytest").unwrap()));
+        assert!(!reachability.contains_key(&PackageName::from_str("ruff").unwrap()));
+    }
+
+    /// Layers follow the depth of a chain, cycles share their entry layer, and unreachable
+    /// nodes are omitted.
+    #[test]
+    fn reachability_layers_chain() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let unreachable = graph.add_node("unreachable");
+        graph.add_edge(root, a, UniversalMarker::TRUE);
+        graph.add_edge(a, b, marker("sys_platform == 'linux'"));
+        graph.add_edge(b, c, UniversalMarker::TRUE);
+        graph.add_edge(root, unreachable, UniversalMarker::FALSE);
+
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        let layers = reachability_layers(&graph, &reachability);
+        assert_eq!(layers[&root], 0);
+        assert_eq!(layers[&a], 1);
+        assert_eq!(layers[&b], 2);
+        assert_eq!(layers[&c], 3);
+        assert!(!layers.contains_key(&unreachable));
+
+        // Closing the cycle `a -> b -> c -> a` puts the whole cycle on its entry layer.
+        graph.add_edge(c, a, UniversalMarker::TRUE);
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        let layers = reachability_layers(&graph, &reachability);
+        assert_eq!(layers[&a], 1);
+        assert_eq!(layers[&b], 1);
+        assert_eq!(layers[&c], 1);
+    }
+
+    /// The closure from a mid-graph node is relative to that node, ignoring the markers on the
+    /// path from the global root.
+    #[test]
+    fn transitive_closure_markers_mid_graph() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        graph.add_edge(root, c, UniversalMarker::TRUE);
+        graph.add_edge(a, b, marker("python_full_version >= '3.12'"));
+        graph.add_edge(a, c, marker("sys_platform == 'win32'"));
+        graph.add_edge(b, c, marker("implementation_name == 'cpython'"));
+
+        let closure: FxHashMap<NodeIndex, UniversalMarker> = transitive_closure_markers(&graph, a);
+        assert_eq!(closure.len(), 3);
+        assert!(!closure.contains_key(&root));
+        assert_eq!(closure[&a], UniversalMarker::TRUE);
+        assert_eq!(closure[&b], marker("python_full_version >= '3.12'"));
+        assert_eq!(
+            closure[&c],
+            marker(
+                "sys_platform == 'win32' \
+                 or (python_full_version >= '3.12' and implementation_name == 'cpython')"
+            )
+        );
+
+        // From the global root, `c` is a direct dependency, so it is always reachable.
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        assert_eq!(reachability[&c], UniversalMarker::TRUE);
+        assert_eq!(
+            reachability[&b],
+            marker("sys_platform == 'linux' and python_full_version >= '3.12'")
+        );
+    }
+
+    /// A node that is only reachable under Python 3.11+ is only installed on Python 3.11+, within
+    /// the global `requires-python`.
+    #[test]
+    fn effective_requires_python_narrowed() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let unreachable = graph.add_node("unreachable");
+        graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        graph.add_edge(
+            a,
+            b,
+            marker("python_version >= '3.11' and implementation_name == 'cpython'"),
+        );
+        graph.add_edge(root, unreachable, UniversalMarker::FALSE);
+
+        let global = MarkerTree::from_str("python_full_version >= '3.9'").unwrap();
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        let requires_python = effective_requires_python(&graph, &reachability, global);
+        assert_eq!(requires_python[&root], global);
+        assert_eq!(requires_python[&a], global);
+        assert_eq!(
+            requires_python[&b],
+            MarkerTree::from_str("python_full_version >= '3.11'").unwrap()
+        );
+        assert!(!requires_python.contains_key(&unreachable));
+    }
+
+    /// Only the subtree behind the `docs` group requires the group to be active, and the
+    /// PEP 508 and extra portions of the markers are dropped.
+    #[test]
+    fn group_reachability_docs_subtree() {
+        let project = PackageName::from_str("project").unwrap();
+        let docs = GroupName::from_str("docs").unwrap();
+        let foo = ExtraName::from_str("foo").unwrap();
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let anyio = graph.add_node("anyio");
+        let sphinx = graph.add_node("sphinx");
+        let jinja2 = graph.add_node("jinja2");
+        graph.add_edge(
+            root,
+            anyio,
+            UniversalMarker::new(
+                marker("sys_platform == 'linux'").pep508(),
+                ConflictMarker::extra(&project, &foo),
+            ),
+        );
+        graph.add_edge(
+            root,
+            sphinx,
+            UniversalMarker::new(MarkerTree::TRUE, ConflictMarker::group(&project, &docs)),
+        );
+        graph.add_edge(sphinx, jinja2, marker("python_full_version >= '3.9'"));
+
+        let groups = group_reachability(&graph, &[]);
+        assert_eq!(groups[&root], MarkerTree::TRUE);
+        assert_eq!(groups[&anyio], MarkerTree::TRUE);
+        let docs_marker =
+            UniversalMarker::new(MarkerTree::TRUE, ConflictMarker::group(&project, &docs))
+                .combined();
+        assert_eq!(groups[&sphinx], docs_marker);
+        assert_eq!(groups[&jinja2], docs_marker);
+    }
+
+    /// Identical maps match, while a widened marker and packages that only appear on one side
+    /// are reported.
+    #[test]
+    fn reachability_matches_widened() {
+        let recorded = FxHashMap::from_iter([
+            ("anyio", marker("sys_platform == 'linux'")),
+            ("idna", UniversalMarker::TRUE),
+            ("sniffio", marker("python_full_version >= '3.9'")),
+        ]);
+        assert_eq!(reachability_matches(&recorded, &recorded.clone()), Ok(()));
+
+        let mut computed = recorded.clone();
+        computed.insert(
+            "anyio",
+            marker("sys_platform == 'linux' or sys_platform == 'darwin'"),
+        );
+        computed.remove("sniffio");
+        computed.insert("typing-extensions", UniversalMarker::TRUE);
+        assert_eq!(
+            reachability_matches(&recorded, &computed),
+            Err(vec![
+                MarkerDrift::Changed {
+                    id: "anyio",
+                    recorded: marker("sys_platform == 'linux'"),
+                    computed: marker("sys_platform == 'linux' or sys_platform == 'darwin'"),
+                },
+                MarkerDrift::OnlyRecorded("sniffio"),
+                MarkerDrift::OnlyComputed("typing-extensions"),
+            ])
+        );
+    }
+
+    #[test]
+    fn reachability_stats_mixed() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let d = graph.add_node("d");
+        let unreachable = graph.add_node("unreachable");
+        graph.add_edge(root, a, UniversalMarker::TRUE);
+        graph.add_edge(
+            root,
+            b,
+            marker("sys_platform == 'linux' and python_full_version >= '3.9'"),
+        );
+        graph.add_edge(root, c, marker("sys_platform == 'linux'"));
+        graph.add_edge(c, d, marker("python_full_version >= '3.9'"));
+        graph.add_edge(root, unreachable, UniversalMarker::FALSE);
+
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        let report = reachability_stats(&reachability);
+        // `b` and `d` share a marker, and `root` and `a` are both `true`.
+        assert_eq!(report.distinct_markers, 4);
+        assert_eq!(report.true_nodes, 2);
+        assert_eq!(report.false_nodes, 1);
+        assert_eq!(report.max_size, 2);
+        assert!((report.mean_size - 5.0 / 6.0).abs() < f64::EPSILON);
+    }
+
+    /// Nodes that are reachable unconditionally are saturated, which doesn't change the markers
+    /// of the nodes that are only reachable conditionally through them.
+    #[test]
+    fn marker_reachability_saturated() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let layer = (0..10)
+            .map(|_| graph.add_node("unconditional"))
+            .collect::<Vec<_>>();
+        let hub = graph.add_node("hub");
+        let linux = graph.add_node("linux");
+        for (index, node) in layer.iter().enumerate() {
+            graph.add_edge(root, *node, UniversalMarker::TRUE);
+            // Every node in the layer depends on the hub, half of them unconditionally.
+            if index % 2 == 0 {
+                graph.add_edge(*node, hub, UniversalMarker::TRUE);
+            } else {
+                graph.add_edge(*node, hub, marker("python_full_version >= '3.12'"));
+            }
+        }
+        graph.add_edge(hub, linux, marker("sys_platform == 'linux'"));
+        // A cycle back into the layer can't widen the saturated nodes.
+        graph.add_edge(linux, layer[0], marker("sys_platform == 'linux'"));
+
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        assert_eq!(reachability.len(), graph.node_count());
+        assert_eq!(reachability[&root], UniversalMarker::TRUE);
+        for node in &layer {
+            assert_eq!(reachability[node], UniversalMarker::TRUE);
+        }
+        assert_eq!(reachability[&hub], UniversalMarker::TRUE);
+        assert_eq!(reachability[&linux], marker("sys_platform == 'linux'"));
+    }
+
+    /// A DFS over the filtered view visits exactly the nodes whose marker evaluates to `true`.
+    #[test]
+    fn reachable_filter_dfs() {
+        let pkg = PackageName::from_str("pkg").unwrap();
+        let foo = ExtraName::from_str("foo").unwrap();
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let linux = graph.add_node("linux");
+        let windows = graph.add_node("windows");
+        let linux_foo = graph.add_node("linux-foo");
+        let windows_child = graph.add_node("windows-child");
+        let always = graph.add_node("always");
+        graph.add_edge(root, linux, marker("sys_platform == 'linux'"));
+        graph.add_edge(root, windows, marker("sys_platform == 'win32'"));
+        graph.add_edge(
+            linux,
+            linux_foo,
+            UniversalMarker::new(MarkerTree::TRUE, ConflictMarker::extra(&pkg, &foo)),
+        );
+        graph.add_edge(windows, windows_child, UniversalMarker::TRUE);
+        graph.add_edge(root, always, UniversalMarker::TRUE);
+
+        let reachability: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        let env = MarkerEnvironment::try_from(MarkerEnvironmentBuilder {
+            implementation_name: "cpython",
+            implementation_version: "3.12.0",
+            os_name: "posix",
+            platform_machine: "x86_64",
+            platform_python_implementation: "CPython",
+            platform_release: "",
+            platform_system: "",
+            platform_version: "",
+            python_full_version: "3.12.0",
+            python_version: "3.12",
+            sys_platform: "linux",
+        })
+        .unwrap();
+        let extras = [(pkg, foo)];
+
+        let filter = reachable_filter(&reachability, &env, &extras);
+        let filtered = NodeFiltered::from_fn(&graph, &filter);
+        let mut visited = FxHashSet::default();
+        let mut dfs = Dfs::new(&filtered, root);
+        while let Some(index) = dfs.next(&filtered) {
+            visited.insert(index);
+        }
+        assert_eq!(
+            visited,
+            FxHashSet::from_iter([root, linux, linux_foo, always])
+        );
+        assert_eq!(
+            visited,
+            graph
+                .node_indices()
+                .filter(|index| filter(*index))
+                .collect()
+        );
+    }
+
+    /// A node behind two platform-exclusive paths is reachable on both platforms, but on no
+    /// platform through every path.
+    #[test]
+    fn marker_reachability_bounds_exclusive_paths() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let linux = graph.add_node("linux");
+        let windows = graph.add_node("windows");
+        let shared = graph.add_node("shared");
+        let nested = graph.add_node("nested");
+        let unreachable = graph.add_node("unreachable");
+        graph.add_edge(root, linux, marker("sys_platform == 'linux'"));
+        graph.add_edge(root, windows, marker("sys_platform == 'win32'"));
+        graph.add_edge(linux, shared, marker("python_full_version >= '3.9'"));
+        graph.add_edge(windows, shared, UniversalMarker::TRUE);
+        graph.add_edge(linux, nested, UniversalMarker::TRUE);
+        graph.add_edge(nested, shared, marker("python_full_version >= '3.10'"));
+        graph.add_edge(root, unreachable, UniversalMarker::FALSE);
+
+        let bounds = marker_reachability_bounds(&graph, &[]);
+        assert_eq!(
+            bounds[&root],
+            (UniversalMarker::TRUE, UniversalMarker::TRUE)
+        );
+        assert_eq!(
+            bounds[&linux],
+            (
+                marker("sys_platform == 'linux'"),
+                marker("sys_platform == 'linux'")
+            )
+        );
+        assert_eq!(
+            bounds[&shared],
+            (
+                marker(
+                    "(sys_platform == 'linux' and python_full_version >= '3.9') \
+                     or sys_platform == 'win32'"
+                ),
+                UniversalMarker::FALSE
+            )
+        );
+        assert_eq!(
+            bounds[&unreachable],
+            (UniversalMarker::FALSE, UniversalMarker::FALSE)
+        );
+
+        // Without the Windows path, every path to `shared` requires Linux and Python 3.10+.
+        let mut graph = graph;
+        let edge = graph.find_edge(windows, shared).unwrap();
+        graph.remove_edge(edge);
+        let bounds = marker_reachability_bounds(&graph, &[]);
+        assert_eq!(
+            bounds[&shared],
+            (
+                marker("sys_platform == 'linux' and python_full_version >= '3.9'"),
+                marker("sys_platform == 'linux' and python_full_version >= '3.10'")
+            )
+        );
+    }
+
+    /// A path that can never be followed doesn't narrow the marker of the node it leads to.
+    #[test]
+    fn marker_reachability_bounds_unsatisfiable_path() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let linux = graph.add_node("linux");
+        let shared = graph.add_node("shared");
+        graph.add_edge(root, linux, marker("sys_platform == 'linux'"));
+        graph.add_edge(linux, shared, marker("sys_platform == 'win32'"));
+        graph.add_edge(root, shared, marker("python_full_version >= '3.10'"));
+
+        let bounds = marker_reachability_bounds(&graph, &[]);
+        assert_eq!(
+            bounds[&shared],
+            (
+                marker("python_full_version >= '3.10'"),
+                marker("python_full_version >= '3.10'")
+            )
+        );
+    }
+
+    /// An edge from a node back to one of its ancestors doesn't narrow the ancestor's marker.
+    #[test]
+    fn marker_reachability_bounds_back_edge() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        graph.add_edge(a, b, marker("python_full_version >= '3.10'"));
+        graph.add_edge(b, a, marker("python_full_version < '3.12'"));
+
+        let bounds = marker_reachability_bounds(&graph, &[]);
+        assert_eq!(
+            bounds[&a],
+            (
+                marker("sys_platform == 'linux'"),
+                marker("sys_platform == 'linux'")
+            )
+        );
+        assert_eq!(
+            bounds[&b],
+            (
+                marker("sys_platform == 'linux' and python_full_version >= '3.10'"),
+                marker("sys_platform == 'linux' and python_full_version >= '3.10'")
+            )
+        );
+
+        // A node below one that is reached through disjoint paths is narrowed to `false` as well,
+        // even if it is also reachable unconditionally.
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        graph.add_edge(root, a, marker("sys_platform == 'win32'"));
+        graph.add_edge(a, b, UniversalMarker::TRUE);
+        graph.add_edge(root, b, UniversalMarker::TRUE);
+
+        let bounds = marker_reachability_bounds(&graph, &[]);
+        assert_eq!(
+            bounds[&a],
+            (
+                marker("sys_platform == 'linux' or sys_platform == 'win32'"),
+                UniversalMarker::FALSE
+            )
+        );
+        assert_eq!(bounds[&b], (UniversalMarker::TRUE, UniversalMarker::FALSE));
+    }
+
+    /// A large graph with only a few distinct markers stores each of them once.
+    #[test]
+    fn marker_reachability_compact_shares_markers() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let linux = marker("sys_platform == 'linux'");
+        let mut nodes = Vec::new();
+        for index in 0..999 {
+            let node = graph.add_node("node");
+            // Cycle between unconditional, Linux-only and macOS-only dependencies. The redundant
+            // `sys_platform != 'win32'` doesn't make for a distinct marker.
+            let edge = match index % 3 {
+                0 => UniversalMarker::TRUE,
+                1 => linux,
+                _ => marker("sys_platform == 'darwin' and sys_platform != 'win32'"),
+            };
+            graph.add_edge(root, node, edge);
+            nodes.push(node);
+        }
+
+        let (markers, indices) = marker_reachability_compact(&graph, &[], &GroupSelection::all());
+        assert_eq!(indices.len(), 1000);
+        assert_eq!(markers.len(), 3);
+        assert_eq!(markers[indices[&root] as usize], UniversalMarker::TRUE);
+        assert_eq!(indices[&nodes[1]], indices[&nodes[4]]);
+        assert_eq!(markers[indices[&nodes[1]] as usize], linux);
+        assert_eq!(
+            markers[indices[&nodes[2]] as usize],
+            marker("sys_platform == 'darwin'")
+        );
+
+        // Each index resolves to the marker that `marker_reachability` computes.
+        let expected: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        for (index, marker) in expected {
+            assert_eq!(markers[indices[&index] as usize], marker);
+        }
+    }
+
+    /// Contracting a virtual extra node connects its predecessors to its successors, with the
+    /// extra assumed to be activated past the virtual node.
+    #[test]
+    fn contract_virtual_nodes_extra_chain() {
+        let project = PackageName::from_str("project").unwrap();
+        let cpu = ExtraName::from_str("cpu").unwrap();
+        let cpu_marker = ConflictMarker::extra(&project, &cpu);
+
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let project_cpu = graph.add_node(dist_node("project", Some("cpu")));
+        let torch = graph.add_node(dist_node("torch", None));
+        let numpy = graph.add_node(dist_node("numpy", None));
+        graph.add_edge(
+            root,
+            project_cpu,
+            UniversalMarker::new(marker("sys_platform == 'linux'").pep508(), cpu_marker),
+        );
+        graph.add_edge(
+            project_cpu,
+            torch,
+            UniversalMarker::new(marker("python_full_version >= '3.9'").pep508(), cpu_marker),
+        );
+        graph.add_edge(torch, numpy, UniversalMarker::TRUE);
+
+        let before = reachability_by_name(&graph);
+        contract_virtual_nodes(&mut graph);
+        let after = reachability_by_name(&graph);
+
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(before, after);
+        // Only `numpy` moved into the slot of the removed node, so `torch` keeps its index.
+        let edge = graph.find_edge(root, torch).unwrap();
+        assert_eq!(
+            graph[edge],
+            UniversalMarker::new(
+                marker("sys_platform == 'linux' and python_full_version >= '3.9'").pep508(),
+                cpu_marker,
+            )
+        );
+    }
+
+    /// A path through a virtual extra node collapses into a direct edge with the conjoined
+    /// marker.
+    #[test]
+    fn real_edges_collapse_virtual_extra() {
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let foo = graph.add_node(dist_node("foo", None));
+        let bar_x = graph.add_node(dist_node("bar", Some("x")));
+        let bar = graph.add_node(dist_node("bar", None));
+        let baz = graph.add_node(dist_node("baz", None));
+        graph.add_edge(root, foo, UniversalMarker::TRUE);
+        graph.add_edge(foo, bar_x, marker("sys_platform == 'linux'"));
+        graph.add_edge(bar_x, bar, UniversalMarker::TRUE);
+        graph.add_edge(bar_x, baz, marker("python_version >= '3.10'"));
+
+        let mut edges = real_edges(&graph).collect::<Vec<_>>();
+        edges.sort_by_key(|(source, target, _)| (*source, *target));
+        assert_eq!(
+            edges,
+            vec![
+                (foo, bar, marker("sys_platform == 'linux'")),
+                (
+                    foo,
+                    baz,
+                    marker("sys_platform == 'linux' and python_version >= '3.10'")
+                ),
+            ]
+        );
+    }
+
+    /// A virtual node reached along several paths is re-visited when its marker widens, so
+    /// the collapsed edge covers every path and not just the first one explored.
+    #[test]
+    fn real_edges_revisit_widened_virtual_node() {
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let foo = graph.add_node(dist_node("foo", None));
+        let a_x = graph.add_node(dist_node("a", Some("x")));
+        let b_y = graph.add_node(dist_node("b", Some("y")));
+        let c_z = graph.add_node(dist_node("c", Some("z")));
+        let a = graph.add_node(dist_node("a", None));
+        let b = graph.add_node(dist_node("b", None));
+        let c = graph.add_node(dist_node("c", None));
+        graph.add_edge(foo, a_x, marker("sys_platform == 'linux'"));
+        graph.add_edge(foo, b_y, marker("sys_platform == 'darwin'"));
+        graph.add_edge(a_x, a, UniversalMarker::TRUE);
+        graph.add_edge(b_y, b, UniversalMarker::TRUE);
+        graph.add_edge(a_x, c_z, UniversalMarker::TRUE);
+        graph.add_edge(b_y, c_z, UniversalMarker::TRUE);
+        graph.add_edge(c_z, c, UniversalMarker::TRUE);
+
+        let (_, _, foo_c) = real_edges(&graph)
+            .find(|(source, target, _)| *source == foo && *target == c)
+            .unwrap();
+        assert_eq!(
+            foo_c,
+            marker("sys_platform == 'linux' or sys_platform == 'darwin'")
+        );
+    }
+
+    /// Driving the solver step by step re-queues the nodes of a cycle only while their markers
+    /// widen, and converges to the result of `marker_reachability`.
+    #[test]
+    fn reachability_solver_steps() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        graph.add_edge(root, b, marker("sys_platform == 'darwin'"));
+        graph.add_edge(a, b, UniversalMarker::TRUE);
+        graph.add_edge(b, a, UniversalMarker::TRUE);
+
+        let mut solver = ReachabilitySolver::new(&graph, &[], &GroupSelection::all());
+        assert_eq!(solver.queue(), &[root]);
+
+        // Relaxing the root queues both children. Edges are visited newest first, so `a` is
+        // queued last and processed next.
+        assert_eq!(solver.step(), Some(root));
+        assert_eq!(solver.queue(), &[b, a]);
+        assert_eq!(solver.reachability()[&a], marker("sys_platform == 'linux'"));
+
+        // Relaxing `a` widens `b` through the cycle, so `b` is queued again.
+        assert_eq!(solver.step(), Some(a));
+        assert_eq!(solver.queue(), &[b, b]);
+        let both = marker("sys_platform == 'linux' or sys_platform == 'darwin'");
+        assert_eq!(solver.reachability()[&b], both);
+
+        // Relaxing `b` widens `a` in turn.
+        assert_eq!(solver.step(), Some(b));
+        assert_eq!(solver.queue(), &[b, a]);
+        assert_eq!(solver.reachability()[&a], both);
+
+        // From here on, nothing widens anymore.
+        assert_eq!(solver.step(), Some(a));
+        assert_eq!(solver.queue(), &[b]);
+        assert_eq!(solver.step(), Some(b));
+        assert_eq!(solver.step(), None);
+
+        let expected: FxHashMap<NodeIndex, UniversalMarker> =
+            marker_reachability(&graph, &[], &GroupSelection::all());
+        assert_eq!(solver.into_map(), expected);
+    }
+
+    /// Only virtual extra nodes have an extra, and only virtual group nodes have a group.
+    #[test]
+    fn resolution_graph_node_extra_and_group() {
+        let root = ResolutionGraphNode::Root;
+        assert_eq!(root.extra(), None);
+        assert_eq!(root.group(), None);
+
+        let base = dist_node("foo", None);
+        assert_eq!(base.extra(), None);
+        assert_eq!(base.group(), None);
+
+        let extra = dist_node("foo", Some("x"));
+        assert_eq!(extra.extra(), Some(&ExtraName::from_str("x").unwrap()));
+        assert_eq!(extra.group(), None);
+
+        let group = group_node("foo", "dev");
+        assert_eq!(group.extra(), None);
+        assert_eq!(group.group(), Some(&GroupName::from_str("dev").unwrap()));
+    }
+
+    /// Imbibing conflicts into the whole graph matches imbibing each edge on its own.
+    #[test]
+    fn imbibe_conflicts_matches_serial() {
+        let foo = PackageName::from_str("foo").unwrap();
+        let x1 = ExtraName::from_str("x1").unwrap();
+        let x2 = ExtraName::from_str("x2").unwrap();
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ConflictSet::pair(
+            ConflictItem::from((foo.clone(), x1.clone())),
+            ConflictItem::from((foo.clone(), x2.clone())),
+        ));
+
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let bar = graph.add_node(dist_node("bar", None));
+        let baz = graph.add_node(dist_node("baz", None));
+        graph.add_edge(
+            root,
+            bar,
+            UniversalMarker::new(
+                MarkerTree::TRUE,
+                ConflictMarker::extra(&foo, &x1)
+                    .negate()
+                    .or(ConflictMarker::extra(&foo, &x2).negate()),
+            ),
+        );
+        graph.add_edge(
+            bar,
+            baz,
+            UniversalMarker::new(
+                MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+                ConflictMarker::extra(&foo, &x1),
+            ),
+        );
+
+        let conflict_marker = ConflictMarker::from_conflicts(&conflicts);
+        let expected = graph
+            .edge_weights()
+            .map(|weight| {
+                let mut weight = *weight;
+                weight.imbibe(conflict_marker);
+                weight
+            })
+            .collect::<Vec<_>>();
+
+        assert!(imbibe_conflicts(&conflicts, &mut graph).is_empty());
+        assert_eq!(graph.edge_weights().copied().collect::<Vec<_>>(), expected);
+        // The edge merely restating the conflict becomes unconditional.
+        assert_eq!(expected[0], UniversalMarker::TRUE);
+    }
+
+    /// An edge two hops below two roots that activate different extras assumes both extras.
+    #[test]
+    fn simplify_conflict_markers_returning_assumptions_two_roots() {
+        let foo = PackageName::from_str("foo").unwrap();
+        let x1 = ExtraName::from_str("x1").unwrap();
+        let x2 = ExtraName::from_str("x2").unwrap();
+        let x1_item = ConflictItem::from((foo.clone(), x1.clone()));
+        let x2_item = ConflictItem::from((foo.clone(), x2.clone()));
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ConflictSet::pair(x1_item.clone(), x2_item.clone()));
+
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let foo_x1 = graph.add_node(dist_node("foo", Some("x1")));
+        let foo_x2 = graph.add_node(dist_node("foo", Some("x2")));
+        let bar = graph.add_node(dist_node("bar", None));
+        let baz = graph.add_node(dist_node("baz", None));
+        graph.add_edge(foo_x1, bar, UniversalMarker::TRUE);
+        graph.add_edge(foo_x2, bar, UniversalMarker::TRUE);
+        let edge = graph.add_edge(
+            bar,
+            baz,
+            UniversalMarker::new(
+                MarkerTree::TRUE,
+                ConflictMarker::extra(&foo, &x1).or(ConflictMarker::extra(&foo, &x2)),
+            ),
+        );
+
+        let assumptions = simplify_conflict_markers_returning_assumptions(&conflicts, &mut graph);
+        assert!(graph[edge].is_true());
+        let assumed = &assumptions[&edge];
+        for item in [x1_item, x2_item] {
+            assert!(assumed.contains(&Inference {
+                item,
+                included: true,
+            }));
+        }
+    }
+
+    /// An edge that no extra or group is known to be activated for isn't recorded.
+    #[test]
+    fn simplify_conflict_markers_returning_assumptions_nothing_assumed() {
+        let foo = PackageName::from_str("foo").unwrap();
+        let x1 = ExtraName::from_str("x1").unwrap();
+        let x2 = ExtraName::from_str("x2").unwrap();
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ConflictSet::pair(
+            ConflictItem::from((foo.clone(), x1)),
+            ConflictItem::from((foo, x2)),
+        ));
+
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let bar = graph.add_node(dist_node("bar", None));
+        let edge = graph.add_edge(root, bar, UniversalMarker::TRUE);
+
+        let assumptions = simplify_conflict_markers_returning_assumptions(&conflicts, &mut graph);
+        assert!(!assumptions.contains_key(&edge));
+        assert!(graph[edge].is_true());
+    }
+
+    /// An edge that requires two conflicting extras at once is reported as contradictory.
+    #[test]
+    fn imbibe_conflicts_contradiction() {
+        let foo = PackageName::from_str("foo").unwrap();
+        let x1 = ExtraName::from_str("x1").unwrap();
+        let x2 = ExtraName::from_str("x2").unwrap();
+        let set = ConflictSet::pair(
+            ConflictItem::from((foo.clone(), x1.clone())),
+            ConflictItem::from((foo.clone(), x2.clone())),
+        );
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(set.clone());
+
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let bar = graph.add_node(dist_node("bar", None));
+        let baz = graph.add_node(dist_node("baz", None));
+        graph.add_edge(
+            root,
+            bar,
+            UniversalMarker::new(MarkerTree::TRUE, ConflictMarker::extra(&foo, &x1)),
+        );
+        let contradictory = graph.add_edge(
+            bar,
+            baz,
+            UniversalMarker::new(
+                MarkerTree::TRUE,
+                ConflictMarker::extra(&foo, &x1).and(ConflictMarker::extra(&foo, &x2)),
+            ),
+        );
+
+        assert_eq!(
+            imbibe_conflicts(&conflicts, &mut graph),
+            vec![ContradictoryConflict {
+                edge: contradictory,
+                sets: vec![set],
+            }]
+        );
+    }
+
+    /// An edge that only a combination of conflict sets contradicts is reported with a minimal
+    /// such combination.
+    #[test]
+    fn imbibe_conflicts_combined_contradiction() {
+        let foo = PackageName::from_str("foo").unwrap();
+        let extra = |name: &str| {
+            let extra = ExtraName::from_str(name).unwrap();
+            (
+                ConflictItem::from((foo.clone(), extra.clone())),
+                ConflictMarker::extra(&foo, &extra),
+            )
+        };
+        let (a, a_marker) = extra("a");
+        let (b, b_marker) = extra("b");
+        let (c, c_marker) = extra("c");
+        let (d, d_marker) = extra("d");
+        let (e, _) = extra("e");
+        let (f, _) = extra("f");
+        let ab = ConflictSet::pair(a, b);
+        let cd = ConflictSet::pair(c, d);
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ab.clone());
+        conflicts.push(ConflictSet::pair(e, f));
+        conflicts.push(cd.clone());
+
+        // Each set on its own leaves one of the two ways to follow the edge.
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let bar = graph.add_node(dist_node("bar", None));
+        let edge = graph.add_edge(
+            root,
+            bar,
+            UniversalMarker::new(
+                MarkerTree::TRUE,
+                a_marker.and(b_marker).or(c_marker.and(d_marker)),
+            ),
+        );
+
+        assert_eq!(
+            imbibe_conflicts(&conflicts, &mut graph),
+            vec![ContradictoryConflict {
+                edge,
+                sets: vec![ab, cd],
+            }]
+        );
+    }
+
+    /// An edge whose conflict marker references an undeclared extra is flagged.
+    #[test]
+    fn assert_conflict_markers_wellformed_undeclared() {
+        let foo = PackageName::from_str("foo").unwrap();
+        let x1 = ExtraName::from_str("x1").unwrap();
+        let x2 = ExtraName::from_str("x2").unwrap();
+        let x3 = ExtraName::from_str("x3").unwrap();
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ConflictSet::pair(
+            ConflictItem::from((foo.clone(), x1.clone())),
+            ConflictItem::from((foo.clone(), x2.clone())),
+        ));
+
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let bar = graph.add_node("bar");
+        let baz = graph.add_node("baz");
+        graph.add_edge(
+            root,
+            bar,
+            UniversalMarker::new(
+                MarkerTree::TRUE,
+                ConflictMarker::extra(&foo, &x1).or(ConflictMarker::extra(&foo, &x2).negate()),
+            ),
+        );
+        assert!(assert_conflict_markers_wellformed(&graph, &conflicts).is_ok());
+
+        let undeclared = graph.add_edge(
+            bar,
+            baz,
+            UniversalMarker::new(
+                MarkerTree::TRUE,
+                ConflictMarker::extra(&foo, &x1).and(ConflictMarker::extra(&foo, &x3)),
+            ),
+        );
+        let ill_formed = assert_conflict_markers_wellformed(&graph, &conflicts).unwrap_err();
+        assert_eq!(ill_formed.len(), 1);
+        let IllFormedEdge::Undeclared { edge, items } = &ill_formed[0] else {
+            panic!("expected an undeclared item, found {:?}", ill_formed[0]);
+        };
+        assert_eq!(*edge, undeclared);
+        assert_eq!(*items, vec![ConflictItem::from((foo, x3))]);
+    }
+
+    /// Both sides of a diamond explain the bottom node, while unsatisfiable paths and cycles
+    /// are skipped.
+    #[test]
+    fn explain_reachability_diamond() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let root = graph.add_node("root");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let root_a = graph.add_edge(root, a, marker("sys_platform == 'linux'"));
+        let root_b = graph.add_edge(root, b, marker("sys_platform == 'win32'"));
+        let a_c = graph.add_edge(a, c, UniversalMarker::TRUE);
+        let b_c = graph.add_edge(b, c, marker("python_full_version >= '3.12'"));
+        // Unsatisfiable paths are omitted.
+        graph.add_edge(a, b, marker("sys_platform == 'win32'"));
+        // Paths don't go around cycles.
+        graph.add_edge(c, a, UniversalMarker::TRUE);
+
+        let paths: Vec<(Vec<EdgeIndex>, UniversalMarker)> = explain_reachability(&graph, c, &[]);
+        assert_eq!(
+            paths,
+            vec![
+                (vec![root_a, a_c], marker("sys_platform == 'linux'")),
+                (
+                    vec![root_b, b_c],
+                    marker("sys_platform == 'win32' and python_full_version >= '3.12'")
+                ),
+            ]
+        );
+
+        // Roots are explained by the empty path.
+        let paths: Vec<(Vec<EdgeIndex>, UniversalMarker)> = explain_reachability(&graph, root, &[]);
+        assert_eq!(paths, vec![(vec![], UniversalMarker::TRUE)]);
+    }
+
+    /// A custom encoding replaces the pairwise one, both for imbibing and for detecting
+    /// contradictions.
+    #[test]
+    fn imbibe_conflicts_with_encoder() {
+        /// An encoding that doesn't exclude anything.
+        struct Permissive;
+
+        impl ConflictEncoder for Permissive {
+            fn encode(&self, _conflicts: &Conflicts) -> ConflictMarker {
+                ConflictMarker::TRUE
+            }
+        }
+
+        let foo = PackageName::from_str("foo").unwrap();
+        let x1 = ExtraName::from_str("x1").unwrap();
+        let x2 = ExtraName::from_str("x2").unwrap();
+        let mut conflicts = Conflicts::empty();
+        conflicts.push(ConflictSet::pair(
+            ConflictItem::from((foo.clone(), x1.clone())),
+            ConflictItem::from((foo.clone(), x2.clone())),
+        ));
+
+        let both = UniversalMarker::new(
+            MarkerTree::TRUE,
+            ConflictMarker::extra(&foo, &x1).and(ConflictMarker::extra(&foo, &x2)),
+        );
+        let mut graph: Graph<ResolutionGraphNode, UniversalMarker> = Graph::new();
+        let root = graph.add_node(ResolutionGraphNode::Root);
+        let bar = graph.add_node(dist_node("bar", None));
+        let edge = graph.add_edge(root, bar, both);
+
+        let mut permissive = graph.clone();
+        assert!(imbibe_conflicts_with(&conflicts, &Permissive, &mut permissive).is_empty());
+        assert_eq!(permissive[edge], both);
+
+        // The default encoding is the pairwise one.
+        let mut pairwise = graph.clone();
+        let contradictions =
+            imbibe_conflicts_with(&conflicts, &PairwiseConflictEncoder, &mut pairwise);
+        assert_eq!(contradictions, imbibe_conflicts(&conflicts, &mut graph));
+        assert_eq!(contradictions.len(), 1);
+        assert_eq!(pairwise[edge], graph[edge]);
+    }
+
+    /// The shortest path wins over a longer one, and among equally short paths the least
+    /// restrictive one wins.
+    #[test]
+    fn shortest_marker_path_diamond() {
+        let mut graph: Graph<&str, UniversalMarker> = Graph::new();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let d = graph.add_node("d");
+        let e = graph.add_node("e");
+        let f = graph.add_node("f");
+        let unreachable = graph.add_node("unreachable");
+        let a_b = graph.add_edge(a, b, marker("sys_platform == 'linux'"));
+        let b_d = graph.add_edge(b, d, UniversalMarker::TRUE);
+        let a_c = graph.add_edge(a, c, UniversalMarker::TRUE);
+        let c_d = graph.add_edge(c, d, marker("python_version >= '3.10'"));
+        graph.add_edge(a, e, UniversalMarker::TRUE);
+        graph.add_edge(e, f, UniversalMarker::TRUE);
+        graph.add_edge(f, d, UniversalMarker::TRUE);
+        graph.add_edge(a, unreachable, UniversalMarker::FALSE);
+
+        // Both `a -> b -> d` and `a -> c -> d` are shorter than `a -> e -> f -> d`, and neither
+        // marker implies the other, so the first one found is kept.
+        let (path, path_marker) = shortest_marker_path(&graph, a, d).unwrap();
+        assert_eq!(path.len(), 2);
+        assert!(
+            (path == vec![a_b, b_d] && path_marker == marker("sys_platform == 'linux'"))
+                || (path == vec![a_c, c_d] && path_marker == marker("python_version >= '3.10'"))
+        );
+
+        // With an unconditional path of the same length, the less restrictive path wins.
+        let g = graph.add_node("g");
+        let a_g = graph.add_edge(a, g, UniversalMarker::TRUE);
+        let g_d = graph.add_edge(g, d, UniversalMarker::TRUE);
+        assert_eq!(
+            shortest_marker_path(&graph, a, d),
+            Some((vec![a_g, g_d], UniversalMarker::TRUE))
+        );
+
+        assert_eq!(
+            shortest_marker_path(&graph, a, a),
+            Some((vec![], UniversalMarker::TRUE))
+        );
+        assert_eq!(shortest_marker_path(&graph, a, unreachable), None);
+        assert_eq!(shortest_marker_path(&graph, d, a), None);
     }
 }
@@ -15,7 +15,7 @@ use uv_normalize::{ExtraName, GroupName, PackageName};
 use uv_pep508::MarkerTree;
 use uv_pypi_types::ConflictItem;
 
-use crate::graph_ops::{Reachable, marker_reachability};
+use crate::graph_ops::{GroupRoot, GroupSelection, Reachable, marker_reachability};
 pub(crate) use crate::lock::export::pylock_toml::PylockTomlPackage;
 pub use crate::lock::export::pylock_toml::{PylockToml, PylockTomlErrorKind};
 pub use crate::lock::export::requirements_txt::RequirementsTxtExport;
@@ -291,7 +291,8 @@ impl<'lock> ExportableRequirements<'lock> {
         let mut reachability = if let Some(conflicts) = conflicts.as_ref() {
             conflict_marker_reachability(&graph, &[], conflicts)
         } else {
-            marker_reachability(&graph, &[])
+            // The selected groups have already been applied while building the graph.
+            marker_reachability(&graph, &[], &GroupSelection::all())
         };
 
         // Collect all packages.
@@ -360,6 +361,13 @@ impl Edge<'_> {
     }
 }
 
+impl GroupRoot for Node<'_> {
+    fn root_group(&self) -> Option<&GroupName> {
+        // Dependency groups are attached to the `Edge::Dev` edges from the root instead.
+        None
+    }
+}
+
 impl Reachable<MarkerTree> for Edge<'_> {
     fn true_marker() -> MarkerTree {
         MarkerTree::TRUE
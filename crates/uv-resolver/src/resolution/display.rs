@@ -64,7 +64,7 @@ impl<'a> DisplayResolutionGraph<'a> {
     ) -> Self {
         for fork_marker in &underlying.fork_markers {
             assert!(
-                fork_marker.conflict().is_true(),
+                fork_marker.is_conflict_trivial(),
                 "found fork marker {fork_marker:?} with non-trivial conflicting marker, \
                  cannot display resolver output with conflicts in requirements.txt format",
             );
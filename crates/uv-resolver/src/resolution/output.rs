@@ -20,15 +20,18 @@ use uv_normalize::{ExtraName, GroupName, PackageName};
 use uv_pep440::{Version, VersionSpecifier};
 use uv_pep508::{MarkerEnvironment, MarkerTree, MarkerTreeKind};
 use uv_pypi_types::{Conflicts, HashDigests, ParsedUrlError, VerbatimParsedUrl, Yanked};
+use uv_warnings::warn_user;
 
-use crate::graph_ops::{marker_reachability, simplify_conflict_markers};
+use crate::graph_ops::{
+    GroupSelection, imbibe_conflicts, marker_reachability, simplify_conflict_markers,
+};
 use crate::pins::FilePins;
 use crate::preferences::Preferences;
 use crate::redirect::url_to_precise;
 use crate::resolution::AnnotatedDist;
 use crate::resolution_mode::ResolutionStrategy;
 use crate::resolver::{Resolution, ResolutionDependencyEdge, ResolutionPackage};
-use crate::universal_marker::{ConflictMarker, UniversalMarker};
+use crate::universal_marker::{UniversalMarker, describe_conflict_item};
 use crate::{
     InMemoryIndex, MetadataResponse, Options, PythonRequirement, ResolveError, VersionsResponse,
 };
@@ -73,6 +76,23 @@ impl ResolutionGraphNode {
         }
     }
 
+    /// Returns the extra of a virtual extra node, or `None` for the root and all other nodes.
+    pub(crate) fn extra(&self) -> Option<&ExtraName> {
+        match self {
+            Self::Root => None,
+            Self::Dist(dist) => dist.extra.as_ref(),
+        }
+    }
+
+    /// Returns the dependency group of a virtual group node, or `None` for the root and all other
+    /// nodes.
+    pub(crate) fn group(&self) -> Option<&GroupName> {
+        match self {
+            Self::Root => None,
+            Self::Dist(dist) => dist.group.as_ref(),
+        }
+    }
+
     pub(crate) fn package_extra_names(&self) -> Option<(&PackageName, &ExtraName)> {
         match self {
             Self::Root => None,
@@ -204,19 +224,38 @@ impl ResolverOutput {
         };
 
         // Compute and apply the marker reachability.
-        let mut reachability = marker_reachability(&graph, &fork_markers);
+        let mut reachability = marker_reachability(&graph, &fork_markers, &GroupSelection::all());
 
         // Apply the reachability to the graph and imbibe world
         // knowledge about conflicts.
-        let conflict_marker = ConflictMarker::from_conflicts(conflicts);
         for index in graph.node_indices() {
             if let ResolutionGraphNode::Dist(dist) = &mut graph[index] {
                 dist.marker = reachability.remove(&index).unwrap_or_default();
-                dist.marker.imbibe(conflict_marker);
             }
         }
-        for weight in graph.edge_weights_mut() {
-            weight.imbibe(conflict_marker);
+        for contradiction in imbibe_conflicts(conflicts, &mut graph) {
+            let (source, target) = graph.edge_endpoints(contradiction.edge).unwrap();
+            warn_user!(
+                "Dependency edge from {} to {} can only be followed when conflicting items are \
+                 activated together: {}",
+                graph[source]
+                    .package_name()
+                    .map_or_else(|| "the root".to_string(), ToString::to_string),
+                graph[target]
+                    .package_name()
+                    .map_or_else(|| "the root".to_string(), ToString::to_string),
+                contradiction
+                    .sets
+                    .iter()
+                    .map(|set| {
+                        set.iter()
+                            .map(describe_conflict_item)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            );
         }
 
         simplify_conflict_markers(conflicts, &mut graph);
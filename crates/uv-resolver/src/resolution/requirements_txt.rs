@@ -169,7 +169,7 @@ impl<'dist> RequirementsTxtDist<'dist> {
 
     pub(crate) fn from_annotated_dist(annotated: &'dist AnnotatedDist) -> Self {
         assert!(
-            annotated.marker.conflict().is_true(),
+            annotated.marker.is_conflict_trivial(),
             "found dist {annotated} with non-trivial conflicting marker {marker:?}, \
              which cannot be represented in a `requirements.txt` format",
             marker = annotated.marker,
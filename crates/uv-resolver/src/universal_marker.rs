@@ -2,12 +2,18 @@ use std::borrow::Borrow;
 use std::collections::BTreeSet;
 use std::str::FromStr;
 
+use arcstr::ArcStr;
 use itertools::Itertools;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
+use uv_distribution_types::RequiresPython;
 use uv_normalize::{ExtraName, GroupName, PackageName};
-use uv_pep508::{ExtraOperator, MarkerEnvironment, MarkerExpression, MarkerOperator, MarkerTree};
-use uv_pypi_types::{ConflictItem, ConflictKind, Conflicts, Inference};
+use uv_pep440::{Version, VersionSpecifier};
+use uv_pep508::{
+    ExtraOperator, MarkerEnvironment, MarkerExpression, MarkerOperator, MarkerTree, MarkerValue,
+    MarkerValueExtra, MarkerValueString, MarkerValueVersion,
+};
+use uv_pypi_types::{ConflictItem, ConflictKind, ConflictSet, Conflicts, Inference};
 
 use crate::ResolveError;
 
@@ -23,6 +29,13 @@ use crate::ResolveError;
 ///
 /// A universal marker evaluates to true only when *both* its PEP 508 marker
 /// and its conflict marker evaluate to true.
+///
+/// Since the underlying [`MarkerTree`]s are interned, comparing and hashing
+/// universal markers is cheap: neither walks the marker trees, and two
+/// universal markers are equal exactly when they are semantically equivalent.
+/// This includes known `platform_system` aliases: constructing a marker
+/// already rewrites, e.g., `platform_system == 'Darwin'` as
+/// `sys_platform == 'darwin'`.
 #[derive(Default, Copy, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct UniversalMarker {
     /// The full combined PEP 508 and "conflict" marker.
@@ -83,13 +96,13 @@ pub struct UniversalMarker {
 
 impl UniversalMarker {
     /// A constant universal marker that always evaluates to `true`.
-    pub(crate) const TRUE: Self = Self {
+    pub const TRUE: Self = Self {
         marker: MarkerTree::TRUE,
         pep508: MarkerTree::TRUE,
     };
 
     /// A constant universal marker that always evaluates to `false`.
-    pub(crate) const FALSE: Self = Self {
+    pub const FALSE: Self = Self {
         marker: MarkerTree::FALSE,
         pep508: MarkerTree::FALSE,
     };
@@ -100,6 +113,31 @@ impl UniversalMarker {
         Self::from_combined(pep508_marker)
     }
 
+    /// Creates a new universal marker from its constituent pieces, validating
+    /// that the conflict marker only contains `extra` expressions (which is
+    /// how extras and groups are encoded).
+    ///
+    /// A conflict marker that depends on environment markers, like
+    /// `sys_platform`, mixes PEP 508 logic into the conflict logic and is
+    /// rejected.
+    #[allow(dead_code)]
+    pub(crate) fn from_parts_validated(
+        pep508_marker: MarkerTree,
+        conflict_marker: MarkerTree,
+    ) -> Result<Self, MarkerPartError> {
+        // Removing all non-`extra` expressions is the identity exactly when the
+        // (canonical) marker doesn't depend on any of them.
+        if conflict_marker.only_extras() != conflict_marker {
+            return Err(MarkerPartError::NonConflictExpression(conflict_marker));
+        }
+        Ok(Self::new(
+            pep508_marker,
+            ConflictMarker {
+                marker: conflict_marker,
+            },
+        ))
+    }
+
     /// Creates a new universal marker from a marker that has already been
     /// combined from a PEP 508 and conflict marker.
     pub(crate) fn from_combined(marker: MarkerTree) -> Self {
@@ -112,6 +150,13 @@ impl UniversalMarker {
     /// Combine this universal marker with the one given in a way that unions
     /// them. That is, the updated marker will evaluate to `true` if `self` or
     /// `other` evaluate to `true`.
+    ///
+    /// Since the conflict marker is part of the combined marker, each conflict
+    /// marker stays gated by the PEP 508 marker of the branch it belongs to.
+    /// For example, the union of `sys_platform == 'linux'` with conflict
+    /// marker `extra == 'foo'` and `sys_platform == 'darwin'` with a trivial
+    /// conflict marker still requires `foo` on Linux. Only the projection
+    /// returned by [`UniversalMarker::conflict`] loses this correlation.
     pub(crate) fn or(&mut self, other: Self) {
         self.marker.or(other.marker);
         self.pep508.or(other.pep508);
@@ -120,11 +165,284 @@ impl UniversalMarker {
     /// Combine this universal marker with the one given in a way that
     /// intersects them. That is, the updated marker will evaluate to `true` if
     /// `self` and `other` evaluate to `true`.
+    ///
+    /// Marker trees are interned decision diagrams that are kept in their
+    /// canonical, fully reduced form by every operation. So there's no
+    /// intermediate growth to guard against here: the result of a long
+    /// chain of conjunctions is exactly as compact as the same marker built
+    /// in one step, and any remaining size is inherent to the marker itself.
     pub(crate) fn and(&mut self, other: Self) {
         self.marker.and(other.marker);
         self.pep508.and(other.pep508);
     }
 
+    /// Combine this universal marker with the one given as a logical
+    /// implication. That is, the updated marker will evaluate to `true` if
+    /// this marker evaluates to `false` or the one given evaluates to `true`.
+    ///
+    /// If the updated marker is always `true`, then this marker implies the
+    /// one given.
+    pub(crate) fn implies(&mut self, other: Self) {
+        self.marker.implies(other.marker);
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Combine this universal marker with the given PEP 508 marker in a way
+    /// that intersects them, asserting that no conflict markers are involved.
+    ///
+    /// This is meant for call sites that predate universal resolution, where
+    /// the markers being combined are known to be plain PEP 508 markers. In
+    /// debug builds, this panics if this marker has a non-trivial conflict
+    /// marker, since the caller would otherwise be silently mixing conflict
+    /// markers into a context that doesn't account for them.
+    #[allow(dead_code)]
+    pub(crate) fn and_assuming_no_conflicts(&mut self, other: MarkerTree) {
+        debug_assert!(
+            self.is_conflict_trivial(),
+            "expected universal marker without conflicts, but found {:?}",
+            self.conflict(),
+        );
+        self.marker.and(other);
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Recomputes the PEP 508 marker from the combined marker.
+    ///
+    /// [`UniversalMarker::and`] intersects the PEP 508 markers piecewise, so
+    /// afterwards the PEP 508 marker may be wider than the projection of the
+    /// combined marker. For example, intersecting `extra == 'a'` with
+    /// `extra != 'a'` yields a combined marker that is `false`, yet a PEP 508
+    /// marker that is `true`.
+    pub(crate) fn simplify(&mut self) {
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Simplifies this marker under the assumption that the given fork marker
+    /// holds, e.g., to keep the markers computed for a single fork minimal.
+    ///
+    /// Clauses that are disjoint with the fork are dropped, as are the
+    /// expressions that the fork already implies. The result is only
+    /// equivalent to the original marker within the fork.
+    #[allow(dead_code)]
+    pub(crate) fn specialize(&mut self, fork: Self) {
+        if self.marker.is_true() || self.marker.is_false() {
+            return;
+        }
+
+        let mut specialized = MarkerTree::FALSE;
+        for clause in self.marker.to_dnf() {
+            let mut and = MarkerTree::TRUE;
+            for expr in clause {
+                let expr = MarkerTree::expression(expr);
+                if fork.marker.is_disjoint(expr.negate()) {
+                    continue;
+                }
+                and.and(expr);
+            }
+            if fork.marker.is_disjoint(and) {
+                continue;
+            }
+            specialized.or(and);
+        }
+        self.marker = specialized;
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Intersects this marker with the given fork marker and then
+    /// [specializes](UniversalMarker::specialize) the result to the fork,
+    /// e.g., to propagate an edge marker within a single fork.
+    ///
+    /// Since the expressions implied by the fork are dropped, the result is
+    /// only equivalent to `self and fork` within the fork.
+    #[allow(dead_code)]
+    #[must_use]
+    pub(crate) fn and_fork(self, fork: Self) -> Self {
+        let mut marker = self;
+        marker.and(fork);
+        marker.specialize(fork);
+        marker
+    }
+
+    /// Removes Python version bounds that are already implied by the given
+    /// `requires-python` (see [`RequiresPython::simplify_markers`]).
+    ///
+    /// The result is only equivalent to the original marker under the
+    /// assumption that `requires-python` holds, which is always the case for
+    /// markers in a lockfile.
+    #[allow(dead_code)]
+    pub(crate) fn strip_redundant_python_bounds(&mut self, requires_python: &RequiresPython) {
+        self.marker = requires_python.simplify_markers(self.marker);
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Assumes that the Python version is the given version, e.g., for a
+    /// lock section that targets a single Python version.
+    ///
+    /// This partially evaluates the `python_version` and `python_full_version`
+    /// expressions against the given version: satisfied expressions are
+    /// replaced with `true`, and unsatisfied ones with `false`. (Since
+    /// `python_version` is normalized to `python_full_version` when a marker
+    /// is constructed, both keys are handled the same way.) The remaining
+    /// expressions are preserved.
+    ///
+    /// A two-component version like `3.11` stands for every `3.11.x` release,
+    /// so an expression is only decided if it's decided for all of them. For
+    /// example, `python_full_version >= '3.11.1'` is preserved for `3.11`.
+    #[allow(dead_code)]
+    pub(crate) fn assume_python_version(&mut self, version: &Version) {
+        if self.marker.is_true() || self.marker.is_false() {
+            return;
+        }
+
+        let python_version = assumed_python_version(version);
+        let mut evaluated = MarkerTree::FALSE;
+        'clauses: for clause in self.marker.to_dnf() {
+            let mut and = MarkerTree::TRUE;
+            for expr in clause {
+                let is_python_version = is_python_version_expression(&expr);
+                let expr = MarkerTree::expression(expr);
+                if is_python_version {
+                    if python_version.is_disjoint(expr) {
+                        continue 'clauses;
+                    }
+                    if python_version.is_disjoint(expr.negate()) {
+                        continue;
+                    }
+                }
+                and.and(expr);
+            }
+            evaluated.or(and);
+        }
+        self.marker = evaluated;
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Assumes that the given environment keys have the given values, e.g.,
+    /// to fix `sys_platform` for a per-OS lock section that still spans
+    /// multiple Python versions.
+    ///
+    /// This partially evaluates the expressions on the given keys, like
+    /// [`UniversalMarker::assume_python_version`] does for the Python version:
+    /// clauses with an unsatisfied expression are dropped, and satisfied
+    /// expressions are removed from their clause. Expressions on keys that
+    /// aren't listed remain symbolic, even if the marker algebra knows them to
+    /// be implied by (or disjoint with) an assignment, as is the case for
+    /// `platform_system` and `sys_platform`.
+    ///
+    /// A `python_version` assignment like `3.11` stands for every `3.11.x`
+    /// release, as in [`UniversalMarker::assume_python_version`], so, e.g.,
+    /// `python_full_version >= '3.11.1'` remains symbolic.
+    ///
+    /// Only environment keys can be assigned: assignments for `extra` or a
+    /// quoted string, as well as version assignments that aren't valid
+    /// versions, are ignored.
+    #[allow(dead_code)]
+    pub(crate) fn assume_environment_keys(&mut self, assignments: &[(MarkerValue, String)]) {
+        let assignments = assignments
+            .iter()
+            .filter_map(|(key, value)| {
+                let expr = match key {
+                    MarkerValue::MarkerEnvVersion(MarkerValueVersion::PythonVersion) => {
+                        let version = Version::from_str(value).ok()?;
+                        return Some((
+                            MarkerValue::MarkerEnvVersion(MarkerValueVersion::PythonFullVersion),
+                            assumed_python_version(&version),
+                        ));
+                    }
+                    MarkerValue::MarkerEnvVersion(key) => MarkerExpression::Version {
+                        key: *key,
+                        specifier: VersionSpecifier::equals_version(Version::from_str(value).ok()?),
+                    },
+                    MarkerValue::MarkerEnvString(key) => MarkerExpression::String {
+                        key: *key,
+                        operator: MarkerOperator::Equal,
+                        value: ArcStr::from(value.as_str()),
+                    },
+                    _ => return None,
+                };
+                // Look up the key as it appears in the expression once
+                // normalized, e.g., `python_full_version` for `python_version`.
+                let key = environment_key(&expr)?;
+                Some((key, MarkerTree::expression(expr)))
+            })
+            .collect::<Vec<_>>();
+        if assignments.is_empty() {
+            return;
+        }
+
+        let mut evaluated = MarkerTree::FALSE;
+        'clauses: for clause in self.marker.to_dnf() {
+            let mut and = MarkerTree::TRUE;
+            for expr in clause {
+                let key = environment_key(&expr);
+                let expr = MarkerTree::expression(expr);
+                let mut decided = false;
+                for (_, assigned) in assignments
+                    .iter()
+                    .filter(|(assigned_key, _)| Some(assigned_key) == key.as_ref())
+                {
+                    if assigned.is_disjoint(expr) {
+                        continue 'clauses;
+                    }
+                    decided |= assigned.is_disjoint(expr.negate());
+                }
+                if !decided {
+                    and.and(expr);
+                }
+            }
+            evaluated.or(and);
+        }
+        self.marker = evaluated;
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Splits this marker into one piece per platform, e.g., to generate one
+    /// lock section per operating system.
+    ///
+    /// For each `sys_platform` value in `platforms` (e.g., `linux`, `darwin`
+    /// or `win32`), the piece is this marker restricted to `sys_platform ==
+    /// '<platform>'`, with the then redundant `sys_platform` expressions
+    /// removed. Platforms under which this marker is `false` are omitted.
+    #[allow(dead_code)]
+    pub(crate) fn split_platforms(self, platforms: &[&str]) -> Vec<(String, Self)> {
+        let mut pieces = Vec::with_capacity(platforms.len());
+        for platform in platforms {
+            let sys_platform = MarkerTree::expression(MarkerExpression::String {
+                key: MarkerValueString::SysPlatform,
+                operator: MarkerOperator::Equal,
+                value: ArcStr::from(*platform),
+            });
+            let mut restricted = self.marker;
+            restricted.and(sys_platform);
+            if restricted.is_false() {
+                continue;
+            }
+
+            let mut stripped = MarkerTree::FALSE;
+            for clause in restricted.to_dnf() {
+                let mut and = MarkerTree::TRUE;
+                for expr in clause {
+                    let is_sys_platform = matches!(
+                        expr,
+                        MarkerExpression::String {
+                            key: MarkerValueString::SysPlatform,
+                            ..
+                        }
+                    );
+                    let expr = MarkerTree::expression(expr);
+                    // The expression is redundant if the platform implies it.
+                    if is_sys_platform && sys_platform.is_disjoint(expr.negate()) {
+                        continue;
+                    }
+                    and.and(expr);
+                }
+                stripped.or(and);
+            }
+            pieces.push(((*platform).to_string(), Self::from_combined(stripped)));
+        }
+        pieces
+    }
+
     /// Imbibes the world knowledge expressed by `conflicts` into this marker.
     ///
     /// This will effectively simplify the conflict marker in this universal
@@ -168,6 +486,109 @@ impl UniversalMarker {
         }
     }
 
+    /// Returns true if this marker is satisfiable when the given extra for
+    /// the given package is activated, e.g., to skip the edges that an
+    /// activated extra excludes when planning an install.
+    ///
+    /// This intersects the marker with the extra rather than
+    /// [assuming](UniversalMarker::assume_conflict_item) the extra, since
+    /// assuming an extra only simplifies `extra == ...` expressions. So a
+    /// marker that requires the extra to be inactive is correctly reported
+    /// as not overlapping.
+    #[allow(dead_code)]
+    pub(crate) fn overlaps_extra(self, package: &PackageName, extra: &ExtraName) -> bool {
+        let mut marker = self;
+        marker.and(Self::new(
+            MarkerTree::TRUE,
+            ConflictMarker::extra(package, extra),
+        ));
+        !marker.is_false()
+    }
+
+    /// Returns the maximal sets of extras of the given package from
+    /// `universe` under which the conflict marker of this universal marker is
+    /// satisfiable, e.g., to generate one `requirements.txt` per
+    /// combination of extras when splitting on conflicts.
+    ///
+    /// A set is satisfiable if the conflict marker can be `true` when exactly
+    /// the extras in the set are activated, and the other extras in
+    /// `universe` are not. (Extras outside of `universe` are unconstrained.)
+    /// A satisfiable set is only returned if no strict superset is
+    /// satisfiable, too.
+    ///
+    /// The sets are enumerated depth-first, activating each extra before
+    /// trying without it, so a branch is pruned as soon as its activations
+    /// contradict the conflict marker or it can only lead to subsets of a set
+    /// that was already found. Since the number of sets can still grow
+    /// exponentially, at most [`MAX_SATISFIABLE_EXTRA_SETS`] sets are
+    /// returned.
+    #[allow(dead_code)]
+    pub(crate) fn satisfiable_extra_sets(
+        self,
+        package: &PackageName,
+        universe: &[ExtraName],
+    ) -> Vec<FxHashSet<ExtraName>> {
+        fn enumerate<'a>(
+            conflict: ConflictMarker,
+            package: &PackageName,
+            universe: &'a [ExtraName],
+            index: usize,
+            active: &mut Vec<&'a ExtraName>,
+            found: &mut Vec<FxHashSet<ExtraName>>,
+        ) {
+            if conflict.is_false() || found.len() >= MAX_SATISFIABLE_EXTRA_SETS {
+                return;
+            }
+            // If even activating all remaining extras can only lead to a subset of a set that
+            // was already found, then no set in this branch is maximal.
+            let is_subset_of_found = found.iter().any(|set| {
+                active
+                    .iter()
+                    .copied()
+                    .chain(&universe[index..])
+                    .all(|extra| set.contains(extra))
+            });
+            if is_subset_of_found {
+                return;
+            }
+            let Some(extra) = universe.get(index) else {
+                found.push(active.iter().map(|extra| (*extra).clone()).collect());
+                return;
+            };
+
+            let marker = ConflictMarker::extra(package, extra);
+            active.push(extra);
+            enumerate(
+                conflict.and(marker),
+                package,
+                universe,
+                index + 1,
+                active,
+                found,
+            );
+            active.pop();
+            enumerate(
+                conflict.and(marker.negate()),
+                package,
+                universe,
+                index + 1,
+                active,
+                found,
+            );
+        }
+
+        let mut found = vec![];
+        enumerate(
+            self.conflict(),
+            package,
+            universe,
+            0,
+            &mut Vec::with_capacity(universe.len()),
+            &mut found,
+        );
+        found
+    }
+
     /// Assumes that a given extra/group for the given package is activated.
     ///
     /// This may simplify the conflicting marker component of this universal
@@ -195,6 +616,136 @@ impl UniversalMarker {
         self.pep508 = self.marker.without_extras();
     }
 
+    /// Assumes that the given extra is definitely not activated, e.g., when
+    /// `--no-extra` is passed.
+    ///
+    /// This applies to both plain `extra == '...'` expressions and the
+    /// conflict marker component, for the extra of any package: expressions
+    /// requiring the extra become `false`, and expressions requiring its
+    /// absence become `true`.
+    #[allow(dead_code)]
+    pub(crate) fn assume_false_extra(&mut self, extra: &ExtraName) {
+        let mut names = Vec::new();
+        self.marker.visit_extras(|_, name| {
+            let matches = match ParsedRawExtra::parse(name) {
+                Ok(ParsedRawExtra::Extra { extra: raw, .. }) => raw == extra.as_str(),
+                Ok(ParsedRawExtra::Project { .. } | ParsedRawExtra::Group { .. }) => false,
+                Err(_) => name == extra,
+            };
+            if matches && !names.contains(name) {
+                names.push(name.clone());
+            }
+        });
+        if names.is_empty() {
+            return;
+        }
+
+        // Restrict the marker to the environments where the extras are off,
+        // then drop the (now redundant) negated extras.
+        for name in &names {
+            self.marker
+                .and(MarkerTree::expression(MarkerExpression::Extra {
+                    operator: ExtraOperator::NotEqual,
+                    name: MarkerValueExtra::Extra(name.clone()),
+                }));
+        }
+        self.marker = self
+            .marker
+            .simplify_not_extras_with(|candidate| names.contains(candidate));
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Assumes that the given extras are activated and that the given
+    /// forbidden extras are not, e.g., for a fixed set of `--extra` flags.
+    ///
+    /// Each extra is scoped to the package that it's enabled for, rather than
+    /// given as a bare [`ExtraName`], since several packages in a workspace
+    /// can define an extra with the same name, and the conflict markers encode
+    /// the package alongside the extra. This may simplify the conflicting
+    /// marker component of this universal marker.
+    #[allow(dead_code)]
+    pub(crate) fn restrict_to_extras<P, E>(&mut self, active: &[(P, E)], forbidden: &[(P, E)])
+    where
+        P: Borrow<PackageName>,
+        E: Borrow<ExtraName>,
+    {
+        let active = active
+            .iter()
+            .map(|(package, extra)| encode_package_extra(package.borrow(), extra.borrow()))
+            .collect::<Vec<ExtraName>>();
+        let forbidden = forbidden
+            .iter()
+            .map(|(package, extra)| encode_package_extra(package.borrow(), extra.borrow()))
+            .collect::<Vec<ExtraName>>();
+        self.marker = self
+            .marker
+            .simplify_extras_with(|candidate| active.contains(candidate))
+            .simplify_not_extras_with(|candidate| forbidden.contains(candidate));
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Assumes that every conflicting extra for which `keep` returns `true`
+    /// is activated, e.g., to simplify against a set of known-active extras
+    /// in a single pass.
+    ///
+    /// The predicate is called with the encoded extra names that appear in
+    /// the conflict marker component (see [`ConflictMarker::extra`]), and
+    /// should return `true` for the extras that are assumed active. Extras
+    /// for which it returns `false` are left as-is; they are not assumed to
+    /// be inactive.
+    pub(crate) fn simplify_conflict_extras_with(&mut self, keep: impl Fn(&ExtraName) -> bool) {
+        self.marker = self.marker.simplify_extras_with(keep);
+        self.pep508 = self.marker.without_extras();
+    }
+
+    /// Renames every extra referenced by this marker through `f`, e.g., to
+    /// migrate markers after an extra has been renamed.
+    ///
+    /// This rewrites both the extras in the conflict marker component (i.e.,
+    /// the extra part of an encoded `extra-{len}-{package}-{extra}`) and any
+    /// plain `extra == "..."` expression. Conflict markers for groups and
+    /// projects are preserved as-is.
+    #[allow(dead_code)]
+    pub(crate) fn map_extras(&mut self, f: impl Fn(&ExtraName) -> ExtraName) {
+        if self.marker.is_true() || self.marker.is_false() {
+            return;
+        }
+
+        let mut mapped = MarkerTree::FALSE;
+        for clause in self.marker.to_dnf() {
+            let mut and = MarkerTree::TRUE;
+            for expr in clause {
+                let expr = match expr {
+                    MarkerExpression::Extra {
+                        name: MarkerValueExtra::Extra(name),
+                        operator,
+                    } => {
+                        let name = match ParsedRawExtra::parse(&name)
+                            .and_then(|parsed| parsed.to_conflict_item())
+                        {
+                            Ok(item) => match item.kind() {
+                                ConflictKind::Extra(extra) => {
+                                    encode_package_extra(item.package(), &f(extra))
+                                }
+                                ConflictKind::Group(_) | ConflictKind::Project => name,
+                            },
+                            Err(_) => f(&name),
+                        };
+                        MarkerExpression::Extra {
+                            name: MarkerValueExtra::Extra(name),
+                            operator,
+                        }
+                    }
+                    expr => expr,
+                };
+                and.and(MarkerTree::expression(expr));
+            }
+            mapped.or(and);
+        }
+        self.marker = mapped;
+        self.pep508 = self.marker.without_extras();
+    }
+
     /// Assumes that the "production" dependencies for the given project are
     /// activated.
     ///
@@ -269,6 +820,66 @@ impl UniversalMarker {
         self.pep508 = self.marker.without_extras();
     }
 
+    /// Returns the conjunction of the clauses shared by the two given
+    /// markers, e.g., to factor `sys_platform == 'linux'` out of sibling
+    /// markers.
+    ///
+    /// A clause is shared if it appears in every branch of the disjunctive
+    /// normal form of both markers. The result is thus implied by both
+    /// markers, but it is a structural approximation: clauses that are
+    /// equivalent but written differently (e.g., version ranges that are
+    /// split differently) are not recognized as shared. If nothing is shared,
+    /// the result is `true`.
+    #[allow(dead_code)]
+    pub(crate) fn common_factor(a: Self, b: Self) -> Self {
+        if a.is_false() && b.is_false() {
+            return Self::FALSE;
+        }
+
+        let mut shared: Option<Vec<MarkerExpression>> = None;
+        for clause in [a, b]
+            .into_iter()
+            .filter(|marker| !marker.is_false())
+            .flat_map(|marker| marker.marker.to_dnf())
+        {
+            match shared {
+                None => shared = Some(clause),
+                Some(ref mut shared) => shared.retain(|expr| clause.contains(expr)),
+            }
+        }
+
+        let mut factor = MarkerTree::TRUE;
+        for expr in shared.unwrap_or_default() {
+            factor.and(MarkerTree::expression(expr));
+        }
+        Self::from_combined(factor)
+    }
+
+    /// Checks whether the given markers partition the space of all
+    /// environments, e.g., to validate the forks of `tool.uv.environments`.
+    ///
+    /// That is, whether the markers are pairwise disjoint and their union is
+    /// `true`. Otherwise, the result reports the overlapping pairs and the
+    /// region that isn't covered by any marker.
+    #[allow(dead_code)]
+    pub(crate) fn is_partition(markers: &[Self]) -> PartitionResult {
+        let overlapping = markers
+            .iter()
+            .enumerate()
+            .tuple_combinations()
+            .filter(|((_, a), (_, b))| !a.is_disjoint(**b))
+            .map(|((i, _), (j, _))| (i, j))
+            .collect();
+        let covered = markers.iter().fold(MarkerTree::FALSE, |mut acc, marker| {
+            acc.or(marker.marker);
+            acc
+        });
+        PartitionResult {
+            overlapping,
+            uncovered: Self::from_combined(covered.negate()),
+        }
+    }
+
     /// Returns true if this universal marker will always evaluate to `true`.
     pub(crate) fn is_true(self) -> bool {
         self.marker.is_true()
@@ -279,6 +890,78 @@ impl UniversalMarker {
         self.marker.is_false()
     }
 
+    /// Returns an approximation of the size of this marker: the number of
+    /// expressions in its disjunctive normal form.
+    ///
+    /// Both `true` and `false` have a size of zero.
+    pub(crate) fn approx_size(self) -> usize {
+        if self.marker.is_true() || self.marker.is_false() {
+            return 0;
+        }
+        self.marker.to_dnf().iter().map(Vec::len).sum()
+    }
+
+    /// Returns the top-level conjuncts of this marker, e.g., to render
+    /// `sys_platform == 'linux' and python_full_version >= '3.9'` as one line
+    /// per requirement.
+    ///
+    /// The marker is decomposed one level deep: each expression that is
+    /// shared by all clauses of its disjunctive normal form is a conjunct of
+    /// its own (in the order of the first clause), and the disjunction of the
+    /// remainder of the clauses, if it isn't `true`, is a final conjunct. This
+    /// applies to the combined marker, so conflict markers (e.g. an activated
+    /// extra) are decomposed like any other expression. The conjunction of the
+    /// returned markers is this marker; `true` has no conjuncts.
+    #[allow(dead_code)]
+    pub(crate) fn as_conjuncts(self) -> Vec<MarkerTree> {
+        if self.marker.is_true() {
+            return vec![];
+        }
+        if self.marker.is_false() {
+            return vec![MarkerTree::FALSE];
+        }
+
+        let dnf = self.marker.to_dnf();
+        let common = dnf[0]
+            .iter()
+            .filter(|expr| dnf[1..].iter().all(|clause| clause.contains(expr)))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut rest = MarkerTree::FALSE;
+        for clause in dnf {
+            let mut and = MarkerTree::TRUE;
+            for expr in clause {
+                if !common.contains(&expr) {
+                    and.and(MarkerTree::expression(expr));
+                }
+            }
+            rest.or(and);
+        }
+
+        let mut conjuncts = common
+            .into_iter()
+            .map(MarkerTree::expression)
+            .collect::<Vec<_>>();
+        if !rest.is_true() {
+            conjuncts.push(rest);
+        }
+        conjuncts
+    }
+
+    /// Returns true if the projected conflict marker (see
+    /// [`UniversalMarker::conflict`]) is `true`.
+    ///
+    /// Since the projection loses the correlation between the PEP 508 and
+    /// conflict markers, this doesn't imply that the marker is independent of
+    /// activated extras and groups: `(sys_platform == 'linux' and extra ==
+    /// 'foo') or sys_platform == 'darwin'` is conflict-trivial, too. Use
+    /// [`UniversalMarker::minimize`] to check that a marker is expressible as
+    /// a PEP 508 marker.
+    pub(crate) fn is_conflict_trivial(self) -> bool {
+        self.conflict().is_true()
+    }
+
     /// Returns true if this universal marker is disjoint with the one given.
     ///
     /// Two universal markers are disjoint when it is impossible for them both
@@ -287,15 +970,80 @@ impl UniversalMarker {
         self.marker.is_disjoint(other.marker)
     }
 
+    /// Returns true if this universal marker is satisfied in every environment
+    /// covered by the given fork markers, regardless of which extras and
+    /// groups are activated.
+    ///
+    /// This is weaker than [`UniversalMarker::is_true`]: when the resolver
+    /// environments are restricted (e.g., with `tool.uv.environments`), a
+    /// marker like `sys_platform == 'linux'` covers every relevant
+    /// environment if all forks are Linux-only. If there are no forks, every
+    /// environment is relevant.
+    #[allow(dead_code)]
+    pub(crate) fn covers_all_environments(self, fork_markers: &[UniversalMarker]) -> bool {
+        if !self.is_conflict_trivial() {
+            return false;
+        }
+        let forks = if fork_markers.is_empty() {
+            MarkerTree::TRUE
+        } else {
+            fork_markers
+                .iter()
+                .fold(MarkerTree::FALSE, |mut acc, fork_marker| {
+                    acc.or(fork_marker.pep508());
+                    acc
+                })
+        };
+        forks.is_disjoint(self.marker.negate())
+    }
+
     /// Returns true if this universal marker is satisfied by the given marker
     /// environment.
     ///
     /// This should only be used when evaluating a marker that is known not to
     /// have any extras. For example, the PEP 508 markers on a fork.
+    ///
+    /// This is equivalent to [`UniversalMarker::evaluate`] with no activated
+    /// projects, extras or groups, but avoids encoding and collecting them.
+    /// Note that it always evaluates the combined marker: even when the
+    /// conflict marker is trivially true, the combined marker may still
+    /// depend on extras, so evaluating only the PEP 508 marker would not be
+    /// equivalent.
     pub(crate) fn evaluate_no_extras(self, env: &MarkerEnvironment) -> bool {
         self.marker.evaluate(env, &[])
     }
 
+    /// Evaluates this universal marker against each of the given marker
+    /// environments, with the given activated extras, e.g., for a matrix of
+    /// Python versions.
+    ///
+    /// The result for each environment is the same as for
+    /// [`UniversalMarker::evaluate`] with the extras, but no projects or groups,
+    /// activated. Each extra must be scoped to the package that it's enabled
+    /// for.
+    #[allow(dead_code)]
+    pub(crate) fn evaluate_all<P, E>(self, envs: &[(MarkerEnvironment, Vec<(P, E)>)]) -> Vec<bool>
+    where
+        P: Borrow<PackageName>,
+        E: Borrow<ExtraName>,
+    {
+        // Trivial markers don't need to walk the tree at all.
+        if self.marker.is_true() || self.marker.is_false() {
+            return vec![self.marker.is_true(); envs.len()];
+        }
+
+        let mut extras = Vec::new();
+        envs.iter()
+            .map(|(env, activated)| {
+                extras.clear();
+                extras.extend(activated.iter().map(|(package, extra)| {
+                    encode_package_extra(package.borrow(), extra.borrow())
+                }));
+                self.marker.evaluate(env, &extras)
+            })
+            .collect()
+    }
+
     /// Returns true if this universal marker is satisfied by the given marker
     /// environment and list of activated extras and groups.
     ///
@@ -359,10 +1107,253 @@ impl UniversalMarker {
     /// producing different versions of the same package), then one should
     /// always use a universal marker since it accounts for all possible ways
     /// for a package to be installed.
+    ///
+    /// The PEP 508 marker never contains `extra` expressions: every `extra`
+    /// expression, including the encoded conflict markers, is projected out
+    /// of the combined marker. This over-approximates the marker, i.e., it is
+    /// `true` in every environment the platform and Python version allow,
+    /// regardless of which extras (or groups) are activated. For example,
+    /// `sys_platform == 'linux' and extra == 'x'` becomes
+    /// `sys_platform == 'linux'`, and so does `sys_platform == 'linux' and
+    /// extra != 'x'`.
     pub fn pep508(self) -> MarkerTree {
         self.pep508
     }
 
+    /// Returns the Python version portion of the PEP 508 component of this
+    /// marker, e.g., for comparing against the `requires-python` of a
+    /// package.
+    ///
+    /// All other expressions (including the conflict marker component) are
+    /// replaced with `true`, so e.g. `python_full_version >= '3.11' and
+    /// sys_platform == 'linux'` becomes `python_full_version >= '3.11'`. The
+    /// result is thus an over-approximation: it is `true` for every Python
+    /// version under which this marker can be `true` in some environment.
+    pub(crate) fn requires_python_marker(self) -> MarkerTree {
+        if self.pep508.is_true() || self.pep508.is_false() {
+            return self.pep508;
+        }
+
+        let mut python_versions = MarkerTree::FALSE;
+        for clause in self.pep508.to_dnf() {
+            let mut and = MarkerTree::TRUE;
+            for expr in clause {
+                if is_python_version_expression(&expr) {
+                    and.and(MarkerTree::expression(expr));
+                }
+            }
+            python_versions.or(and);
+        }
+        python_versions
+    }
+
+    /// Splits this universal marker into pairwise disjoint pieces whose union
+    /// is this marker.
+    ///
+    /// Each piece corresponds to one of the top-level `or` branches of the
+    /// PEP 508 marker, intersected with the full combined marker such that it
+    /// retains the conflict marker that applies on that branch. Since branches
+    /// may overlap, each piece excludes the branches before it.
+    ///
+    /// A conjunction (or a marker that is always true) yields itself as the
+    /// only disjunct, and a marker that is always false yields no disjuncts.
+    #[allow(dead_code)]
+    pub(crate) fn disjuncts(self) -> Vec<UniversalMarker> {
+        if self.is_false() {
+            return vec![];
+        }
+        let clauses = self.pep508.to_dnf();
+        if clauses.len() <= 1 {
+            return vec![self];
+        }
+
+        let mut covered = MarkerTree::FALSE;
+        let mut disjuncts = Vec::with_capacity(clauses.len());
+        for clause in clauses {
+            let mut branch = MarkerTree::TRUE;
+            for expr in clause {
+                branch.and(MarkerTree::expression(expr));
+            }
+            let mut disjunct = branch;
+            disjunct.and(covered.negate());
+            disjunct.and(self.marker);
+            covered.or(branch);
+            if !disjunct.is_false() {
+                disjuncts.push(Self::from_combined(disjunct));
+            }
+        }
+        disjuncts
+    }
+
+    /// Returns the PEP 508 marker for this universal marker, along with
+    /// whether any conflict information was dropped to produce it.
+    ///
+    /// This is a deliberately lossy projection for output formats that can't
+    /// represent conflict markers, such as `requirements.txt`. When the
+    /// returned flag is `true`, the PEP 508 marker is wider than this
+    /// universal marker, and callers should warn that the output is
+    /// imprecise.
+    #[allow(dead_code)]
+    pub(crate) fn to_conflict_free(self) -> (MarkerTree, bool) {
+        (self.pep508, self.marker != self.pep508)
+    }
+
+    /// Renders the conflict marker of this universal marker in natural
+    /// language, e.g., `with extra 'cpu' and without extra 'cu124'`, or
+    /// returns `None` if the conflict marker is trivial.
+    ///
+    /// Unlike [`UniversalMarker::explain_conflict`], this only uses the marker
+    /// itself. The clauses of a disjunction are joined with `or`, with each
+    /// clause that has multiple conditions wrapped in parentheses. Plain PEP
+    /// 508 extras, which don't encode a conflict item, are rendered with their
+    /// raw name.
+    #[allow(dead_code)]
+    pub(crate) fn pretty_conflict(self) -> Option<String> {
+        let conflict = self.conflict();
+        if conflict.is_true() {
+            return None;
+        }
+        if conflict.is_false() {
+            return Some("never".to_string());
+        }
+
+        let dnf = conflict.marker.to_dnf();
+        let mut clauses = Vec::with_capacity(dnf.len());
+        for clause in &dnf {
+            let mut conditions = vec![];
+            for expr in clause {
+                let MarkerExpression::Extra { operator, name } = expr else {
+                    continue;
+                };
+                let active = *operator == ExtraOperator::Equal;
+                let Some(item) = conflict_item_for_extra(name) else {
+                    conditions.push(format!(
+                        "{} extra '{name}'",
+                        if active { "with" } else { "without" }
+                    ));
+                    continue;
+                };
+                let condition = match item.kind() {
+                    ConflictKind::Group(_) => format!(
+                        "when {} is {}",
+                        describe_conflict_item(&item),
+                        if active { "active" } else { "inactive" }
+                    ),
+                    ConflictKind::Extra(_) | ConflictKind::Project => format!(
+                        "{} {}",
+                        if active { "with" } else { "without" },
+                        describe_conflict_item(&item)
+                    ),
+                };
+                conditions.push(condition);
+            }
+            let conditions = conditions.join(" and ");
+            if dnf.len() > 1 && clause.len() > 1 {
+                clauses.push(format!("({conditions})"));
+            } else {
+                clauses.push(conditions);
+            }
+        }
+        Some(clauses.join(" or "))
+    }
+
+    /// Returns the PEP 508 marker that is equivalent to this marker, e.g.,
+    /// for a `requirements.txt` export, or an error if this marker depends
+    /// on any activated extras or groups.
+    ///
+    /// Marker trees are kept in a canonical, fully reduced form, so the
+    /// returned marker is already minimal: rendering it (e.g., with
+    /// [`MarkerTree::try_to_string`]) removes redundant clauses and terms.
+    /// Markers that depend on activated extras or groups can't be expressed
+    /// in PEP 508 and need to be split, e.g., per fork, instead.
+    #[allow(dead_code)]
+    pub(crate) fn minimize(self) -> Result<MarkerTree, NonTrivialConflictError> {
+        // Note that checking `conflict` isn't sufficient, since it loses the
+        // correlation between the PEP 508 and conflict markers, e.g., it's
+        // trivial for `(sys_platform == 'linux' and extra == 'foo') or
+        // sys_platform == 'darwin'`.
+        if self.marker != self.pep508 {
+            return Err(NonTrivialConflictError(self));
+        }
+        Ok(self.pep508)
+    }
+
+    /// Returns a human-readable explanation of the conditions on activated
+    /// extras and groups under which this marker is satisfied, or `None` if
+    /// the conflict marker is trivial.
+    ///
+    /// Any declared conflict sets that involve the mentioned extras and groups
+    /// are included in the explanation, since they are usually why the
+    /// condition exists in the first place. Plain PEP 508 extras, which don't
+    /// encode a conflict item, are explained with their raw name.
+    #[allow(dead_code)]
+    pub(crate) fn explain_conflict(self, conflicts: &Conflicts) -> Option<String> {
+        let conflict = self.conflict();
+        if conflict.is_true() {
+            return None;
+        }
+        if conflict.is_false() {
+            return Some("never installed".to_string());
+        }
+
+        let mut mentioned = BTreeSet::new();
+        let mut clauses = vec![];
+        for clause in conflict.marker.to_dnf() {
+            let mut conditions = vec![];
+            for expr in clause {
+                let MarkerExpression::Extra { operator, name } = expr else {
+                    continue;
+                };
+                let state = match operator {
+                    ExtraOperator::Equal => "active",
+                    ExtraOperator::NotEqual => "inactive",
+                };
+                let Some(item) = conflict_item_for_extra(&name) else {
+                    conditions.push(format!("extra '{name}' is {state}"));
+                    continue;
+                };
+                conditions.push(format!("{} is {state}", describe_conflict_item(&item)));
+                mentioned.insert(item);
+            }
+            clauses.push(conditions.join(" and "));
+        }
+
+        let mut explanation = format!("only installed when {}", clauses.join(", or when "));
+        let exclusions = conflicts
+            .iter()
+            .filter(|set| mentioned.iter().any(|item| set.contains_item(item)))
+            .map(|set| {
+                // The items of a conflict set are sorted, so the names are
+                // listed in sorted order rather than in declaration order.
+                let items = set.iter().collect::<Vec<_>>();
+                let names = if items.iter().all(|item| item.extra().is_some()) {
+                    let names = items
+                        .iter()
+                        .map(|item| format!("'{}'", item.extra().unwrap()))
+                        .collect::<Vec<_>>();
+                    format!("extras {}", join_with_and(&names))
+                } else if items.iter().all(|item| item.group().is_some()) {
+                    let names = items
+                        .iter()
+                        .map(|item| format!("'{}'", item.group().unwrap()))
+                        .collect::<Vec<_>>();
+                    format!("groups {}", join_with_and(&names))
+                } else {
+                    let names = items
+                        .iter()
+                        .map(|item| describe_conflict_item(item))
+                        .collect::<Vec<_>>();
+                    join_with_and(&names)
+                };
+                format!("{names} are declared mutually exclusive")
+            })
+            .collect::<Vec<_>>();
+        if !exclusions.is_empty() {
+            explanation.push_str(&format!(" ({})", exclusions.join("; ")));
+        }
+        Some(explanation)
+    }
+
     /// Returns the non-PEP 508 marker expression that represents conflicting
     /// extras/groups.
     ///
@@ -379,18 +1370,233 @@ impl UniversalMarker {
             marker: self.marker.only_extras(),
         }
     }
-}
-
-impl std::fmt::Debug for UniversalMarker {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        std::fmt::Debug::fmt(&self.marker, f)
-    }
-}
+
+    /// Returns the dependency group activation portion of this universal
+    /// marker, i.e., the conflict marker projected onto the expressions for
+    /// dependency groups.
+    ///
+    /// For example, a marker that is only `true` when the `docs` group is
+    /// activated returns the encoded `extra == 'group-..-docs'` expression.
+    /// If the marker doesn't depend on the activation of any group, the
+    /// result is `true` (or `false` if the marker is never `true`).
+    pub(crate) fn group_activation(self) -> MarkerTree {
+        if self.marker.is_true() || self.marker.is_false() {
+            return self.marker;
+        }
+
+        let mut groups = MarkerTree::FALSE;
+        for clause in self.marker.to_dnf() {
+            let mut and = MarkerTree::TRUE;
+            for expr in clause {
+                let MarkerExpression::Extra {
+                    name: MarkerValueExtra::Extra(ref name),
+                    ..
+                } = expr
+                else {
+                    continue;
+                };
+                if matches!(
+                    ParsedRawExtra::parse(name),
+                    Ok(ParsedRawExtra::Group { .. })
+                ) {
+                    and.and(MarkerTree::expression(expr));
+                }
+            }
+            groups.or(and);
+        }
+        groups
+    }
+}
+
+/// Whether a set of markers partitions the space of all environments, as
+/// returned by [`UniversalMarker::is_partition`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PartitionResult {
+    /// The pairs of indices of markers that overlap.
+    pub(crate) overlapping: Vec<(usize, usize)>,
+    /// The environments that aren't covered by any marker, or `false` if
+    /// the markers are exhaustive.
+    pub(crate) uncovered: UniversalMarker,
+}
+
+impl PartitionResult {
+    /// Returns true if the markers are pairwise disjoint and exhaustive.
+    pub(crate) fn is_partition(&self) -> bool {
+        self.overlapping.is_empty() && self.uncovered.is_false()
+    }
+}
+
+/// An error when constructing a [`UniversalMarker`] from its parts.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum MarkerPartError {
+    #[error(
+        "Expected a conflict marker with only `extra` expressions, but found environment markers in `{0:?}`"
+    )]
+    NonConflictExpression(MarkerTree),
+}
+
+/// An error when a [`UniversalMarker`] is expected to be expressible as a
+/// PEP 508 marker, but depends on activated extras or groups.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Expected a marker that doesn't depend on any extras or groups, but found `{0:?}`; split it into one marker per fork instead"
+)]
+pub(crate) struct NonTrivialConflictError(UniversalMarker);
+
+impl std::fmt::Debug for UniversalMarker {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.marker, f)
+    }
+}
+
+impl From<bool> for UniversalMarker {
+    /// Returns [`UniversalMarker::TRUE`] or [`UniversalMarker::FALSE`].
+    fn from(value: bool) -> Self {
+        if value { Self::TRUE } else { Self::FALSE }
+    }
+}
+
+/// Renders the combined marker, e.g., for logging.
+///
+/// If a precision is given (e.g., `{marker:.120}`), the rendered marker is
+/// capped at that many characters, including a trailing ellipsis. The marker
+/// is only ever cut between expressions, never in the middle of one; if not
+/// even the first expression fits, only the ellipsis is rendered.
+impl std::fmt::Display for UniversalMarker {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let rendered = format!("{:?}", self.marker);
+        match f.precision() {
+            Some(max) => f.write_str(&truncate_marker(&rendered, max)),
+            None => f.write_str(&rendered),
+        }
+    }
+}
+
+/// A [`UniversalMarker`] that is compared by bidirectional implication.
+///
+/// [`UniversalMarker`] equality is semantic already, since the underlying
+/// marker trees are canonical. This wrapper makes that explicit for snapshot
+/// tests, such that they keep comparing what the markers match, not how the
+/// marker trees are shaped.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Copy, Clone)]
+pub struct SemanticMarker(pub UniversalMarker);
+
+#[cfg(any(test, feature = "test-util"))]
+impl PartialEq for SemanticMarker {
+    fn eq(&self, other: &Self) -> bool {
+        let implies = |mut antecedent: UniversalMarker, consequent: UniversalMarker| {
+            antecedent.implies(consequent);
+            antecedent.is_true()
+        };
+        implies(self.0, other.0) && implies(other.0, self.0)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Eq for SemanticMarker {}
+
+#[cfg(any(test, feature = "test-util"))]
+impl From<UniversalMarker> for SemanticMarker {
+    fn from(marker: UniversalMarker) -> Self {
+        Self(marker)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl std::ops::Deref for SemanticMarker {
+    type Target = UniversalMarker;
+
+    fn deref(&self) -> &UniversalMarker {
+        &self.0
+    }
+}
+
+/// A small, copyable handle to a [`UniversalMarker`] in a [`MarkerInterner`].
+///
+/// A handle is only meaningful for the interner that created it, and only
+/// until that interner is cleared.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub(crate) struct MarkerHandle(u32);
+
+impl MarkerHandle {
+    /// Returns the position of the marker in the interner, in the order the
+    /// markers were interned.
+    pub(crate) fn index(self) -> u32 {
+        self.0
+    }
+}
+
+/// A registry of universal markers with an explicit lifecycle.
+///
+/// The underlying [`MarkerTree`]s are already interned process-wide, and
+/// that global interner never releases its nodes. This registry instead
+/// lets a long-running process (e.g., a resolver server) scope the markers
+/// for a single resolution: graphs store [`MarkerHandle`]s, and dropping or
+/// clearing the registry invalidates all of them at once.
+///
+/// Equal markers always intern to the same handle.
+#[derive(Debug, Default)]
+pub(crate) struct MarkerInterner {
+    markers: Vec<UniversalMarker>,
+    handles: FxHashMap<UniversalMarker, MarkerHandle>,
+}
+
+#[allow(dead_code)]
+impl MarkerInterner {
+    /// Returns the handle for the given marker, interning it if necessary.
+    pub(crate) fn intern(&mut self, marker: UniversalMarker) -> MarkerHandle {
+        *self.handles.entry(marker).or_insert_with(|| {
+            let handle =
+                MarkerHandle(u32::try_from(self.markers.len()).expect("too many interned markers"));
+            self.markers.push(marker);
+            handle
+        })
+    }
+
+    /// Returns the marker for the given handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle was not created by this interner, or if the
+    /// interner has been cleared since.
+    pub(crate) fn get(&self, handle: MarkerHandle) -> &UniversalMarker {
+        &self.markers[handle.0 as usize]
+    }
+
+    /// Returns the number of distinct markers in this interner.
+    pub(crate) fn len(&self) -> usize {
+        self.markers.len()
+    }
+
+    /// Returns `true` if no markers have been interned.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.markers.is_empty()
+    }
+
+    /// Returns the distinct markers in this interner, such that each handle's
+    /// [`MarkerHandle::index`] is the position of its marker.
+    pub(crate) fn into_markers(self) -> Vec<UniversalMarker> {
+        self.markers
+    }
+
+    /// Removes all markers from this interner, invalidating all handles.
+    pub(crate) fn clear(&mut self) {
+        self.markers.clear();
+        self.handles.clear();
+    }
+}
 
 /// A marker that is only for representing conflicting extras/groups.
 ///
 /// This encapsulates the encoding of extras and groups into PEP 508
 /// markers.
+///
+/// Conflict markers are only ever built from validated package, extra and
+/// group names, so they never contain an arbitrary (invalid) `extra` value.
+/// An arbitrary `extra` value in a combined marker can't be represented
+/// either: it collapses to `false` when the marker is constructed, so
+/// imbibing or assuming conflict items never encounters one.
 #[derive(Default, Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct ConflictMarker {
     marker: MarkerTree,
@@ -414,12 +1620,26 @@ impl ConflictMarker {
         }
         let mut marker = Self::TRUE;
         for set in conflicts.iter() {
-            for (item1, item2) in set.iter().tuple_combinations() {
-                let pair = Self::from_conflict_item(item1)
-                    .negate()
-                    .or(Self::from_conflict_item(item2).negate());
-                marker = marker.and(pair);
-            }
+            marker = marker.and(Self::from_conflict_set(set));
+        }
+        marker
+    }
+
+    /// Creates a new conflict marker from a single declared conflict set,
+    /// i.e., a marker that is true when at most one of its items is
+    /// activated.
+    ///
+    /// The items of a set may mix extras, groups and projects of different
+    /// packages, e.g., extra `a` of `foo` and group `dev` of `bar`. Each kind
+    /// is encoded in its own namespace (see [`ConflictMarker::extra`] and
+    /// [`ConflictMarker::group`]), so the items never alias one another.
+    pub fn from_conflict_set(set: &ConflictSet) -> Self {
+        let mut marker = Self::TRUE;
+        for (item1, item2) in set.iter().tuple_combinations() {
+            let pair = Self::from_conflict_item(item1)
+                .negate()
+                .or(Self::from_conflict_item(item2).negate());
+            marker = marker.and(pair);
         }
         marker
     }
@@ -548,6 +1768,26 @@ impl std::fmt::Debug for ConflictMarker {
     }
 }
 
+/// An encoding of declared conflicts into a conflict marker, e.g., to
+/// experiment with encodings other than the pairwise one.
+pub(crate) trait ConflictEncoder {
+    /// Returns a conflict marker that is `true` exactly when the activated
+    /// items don't violate any of the given conflicts.
+    fn encode(&self, conflicts: &Conflicts) -> ConflictMarker;
+}
+
+/// The default [`ConflictEncoder`], as used by [`ConflictMarker::from_conflicts`]:
+/// for every pair of items in a conflict set, at least one of the two must
+/// not be activated.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PairwiseConflictEncoder;
+
+impl ConflictEncoder for PairwiseConflictEncoder {
+    fn encode(&self, conflicts: &Conflicts) -> ConflictMarker {
+        ConflictMarker::from_conflicts(conflicts)
+    }
+}
+
 /// Encodes the given conflict into a valid `extra` value in a PEP 508 marker.
 fn encode_conflict_item(conflict: &ConflictItem) -> ExtraName {
     match conflict.kind() {
@@ -590,6 +1830,130 @@ fn encode_project(package: &PackageName) -> ExtraName {
     ExtraName::from_owned(format!("project-{package_len}-{package}")).unwrap()
 }
 
+/// Returns true if the given expression is on `python_version` or
+/// `python_full_version`.
+fn is_python_version_expression(expr: &MarkerExpression) -> bool {
+    matches!(
+        expr,
+        MarkerExpression::Version {
+            key: MarkerValueVersion::PythonFullVersion | MarkerValueVersion::PythonVersion,
+            ..
+        } | MarkerExpression::VersionIn {
+            key: MarkerValueVersion::PythonFullVersion | MarkerValueVersion::PythonVersion,
+            ..
+        }
+    )
+}
+
+/// Returns the environment key the given expression is on, if any.
+///
+/// `python_version` is reported as `python_full_version`, since the former
+/// is normalized to the latter when a marker is constructed.
+fn environment_key(expr: &MarkerExpression) -> Option<MarkerValue> {
+    match expr {
+        MarkerExpression::Version { key, .. } | MarkerExpression::VersionIn { key, .. } => {
+            let key = match key {
+                MarkerValueVersion::PythonVersion => MarkerValueVersion::PythonFullVersion,
+                key => *key,
+            };
+            Some(MarkerValue::MarkerEnvVersion(key))
+        }
+        MarkerExpression::String { key, .. } => Some(MarkerValue::MarkerEnvString(*key)),
+        MarkerExpression::List { .. } | MarkerExpression::Extra { .. } => None,
+    }
+}
+
+/// Returns the `python_full_version` marker for an assumed Python version.
+///
+/// A two-component version like `3.11` covers every patch release, i.e.,
+/// `python_full_version == '3.11.*'`, while a longer version is matched
+/// exactly.
+fn assumed_python_version(version: &Version) -> MarkerTree {
+    let specifier = if version.release().len() == 2 {
+        VersionSpecifier::equals_star_version(version.clone())
+    } else {
+        VersionSpecifier::equals_version(version.clone())
+    };
+    MarkerTree::expression(MarkerExpression::Version {
+        key: MarkerValueVersion::PythonFullVersion,
+        specifier,
+    })
+}
+
+/// Describes a conflict item for use in human-readable messages, e.g.,
+/// `extra 'cpu'`.
+pub(crate) fn describe_conflict_item(item: &ConflictItem) -> String {
+    match item.kind() {
+        ConflictKind::Extra(extra) => format!("extra '{extra}'"),
+        ConflictKind::Group(group) => format!("group '{group}'"),
+        ConflictKind::Project => format!("project '{}'", item.package()),
+    }
+}
+
+/// Returns the conflict item encoded in the given `extra` marker value, or
+/// `None` for a plain PEP 508 extra that doesn't encode one.
+fn conflict_item_for_extra(name: &MarkerValueExtra) -> Option<ConflictItem> {
+    ParsedRawExtra::parse(name.as_extra()?)
+        .and_then(|parsed| parsed.to_conflict_item())
+        .ok()
+}
+
+/// The maximum number of sets returned by
+/// [`UniversalMarker::satisfiable_extra_sets`].
+const MAX_SATISFIABLE_EXTRA_SETS: usize = 64;
+
+/// Truncates the given rendered marker to at most `max` characters,
+/// including a trailing ellipsis.
+///
+/// The marker is cut before the last ` and ` or ` or `, or after the last `)`,
+/// that fits outside of quoted values, so that the cut is always between two
+/// expressions.
+fn truncate_marker(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+
+    // Leave room for the ellipsis.
+    let limit = s
+        .char_indices()
+        .nth(max - 1)
+        .map(|(index, _)| index)
+        .unwrap_or(s.len());
+    let mut cut = 0;
+    let mut quote = None;
+    for (index, c) in s.char_indices().take_while(|(index, _)| *index <= limit) {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None => {
+                let rest = &s[index..];
+                if c == ')' {
+                    // Keep the closing parenthesis, if it fits.
+                    if index < limit {
+                        cut = index + 1;
+                    }
+                } else if rest.starts_with(" and ") || rest.starts_with(" or ") {
+                    cut = index;
+                }
+            }
+        }
+    }
+    format!("{}…", &s[..cut])
+}
+
+/// Joins the given items into a list like `a, b and c`.
+fn join_with_and(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [init @ .., last] => format!("{} and {last}", init.join(", ")),
+    }
+}
+
 #[derive(Debug)]
 enum ParsedRawExtra<'a> {
     Project { package: &'a str },
@@ -830,7 +2194,7 @@ mod tests {
     use super::*;
     use std::str::FromStr;
 
-    use uv_pypi_types::ConflictSet;
+    use uv_pep508::MarkerEnvironmentBuilder;
 
     /// Creates a collection of declared conflicts from the sets
     /// provided.
@@ -875,6 +2239,41 @@ mod tests {
         ConflictItem::from((create_package("pkg"), create_extra(name)))
     }
 
+    /// Creates a marker environment for the given Python version and
+    /// platform. The remaining fields are fixed.
+    fn create_env(python_full_version: &str, sys_platform: &str) -> MarkerEnvironment {
+        let python_version = python_full_version
+            .splitn(3, '.')
+            .take(2)
+            .collect::<Vec<_>>()
+            .join(".");
+        MarkerEnvironment::try_from(MarkerEnvironmentBuilder {
+            implementation_name: "cpython",
+            implementation_version: python_full_version,
+            os_name: "posix",
+            platform_machine: "x86_64",
+            platform_python_implementation: "CPython",
+            platform_release: "",
+            platform_system: "",
+            platform_version: "",
+            python_full_version,
+            python_version: &python_version,
+            sys_platform,
+        })
+        .unwrap()
+    }
+
+    /// Evaluates the given universal marker with no activated projects,
+    /// extras or groups.
+    fn evaluate_empty(marker: UniversalMarker, env: &MarkerEnvironment) -> bool {
+        marker.evaluate(
+            env,
+            std::iter::empty::<PackageName>(),
+            std::iter::empty::<(PackageName, ExtraName)>(),
+            std::iter::empty::<(PackageName, GroupName)>(),
+        )
+    }
+
     /// Shortcut for creating a conflict map.
     fn create_known_conflicts<'a>(
         it: impl IntoIterator<Item = (&'a str, &'a str)>,
@@ -1035,4 +2434,1282 @@ mod tests {
         let cm = resolve_conflicts(cm, &known_conflicts);
         assert!(cm.is_false());
     }
+
+    #[test]
+    fn and_assuming_no_conflicts() {
+        let mut marker = UniversalMarker::new(
+            MarkerTree::from_str("python_version >= '3.10'").unwrap(),
+            ConflictMarker::TRUE,
+        );
+        marker.and_assuming_no_conflicts(MarkerTree::from_str("sys_platform == 'linux'").unwrap());
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("python_version >= '3.10' and sys_platform == 'linux'").unwrap()
+        );
+        assert!(marker.conflict().is_true());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "expected universal marker without conflicts")]
+    fn and_assuming_no_conflicts_with_conflicts() {
+        let mut marker = UniversalMarker::new(MarkerTree::TRUE, create_extra_marker("foo"));
+        marker.and_assuming_no_conflicts(MarkerTree::from_str("sys_platform == 'linux'").unwrap());
+    }
+
+    #[test]
+    fn evaluate_no_extras() {
+        let linux = create_env("3.12.1", "linux");
+        let darwin = create_env("3.12.1", "darwin");
+        let pep508 = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+
+        let trivial = UniversalMarker::new(pep508, ConflictMarker::TRUE);
+        let non_trivial = UniversalMarker::new(pep508, create_extra_marker("foo"));
+        let negated = UniversalMarker::new(pep508, create_extra_marker("foo").negate());
+        for marker in [trivial, non_trivial, negated] {
+            for env in [&linux, &darwin] {
+                assert_eq!(
+                    marker.evaluate_no_extras(env),
+                    evaluate_empty(marker, env),
+                    "expected `{marker:?}` to agree with `evaluate`"
+                );
+            }
+        }
+        assert!(trivial.evaluate_no_extras(&linux));
+        assert!(!trivial.evaluate_no_extras(&darwin));
+        assert!(!non_trivial.evaluate_no_extras(&linux));
+        assert!(negated.evaluate_no_extras(&linux));
+    }
+
+    #[test]
+    fn simplify() {
+        // The extras make the piecewise intersection wider than the
+        // projection of the combined marker.
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and extra == 'extra-3-pkg-foo') \
+                 or (sys_platform == 'darwin' and extra != 'extra-3-pkg-foo')",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("sys_platform == 'linux' or sys_platform == 'darwin'").unwrap()
+        );
+        marker.and(UniversalMarker::new(
+            MarkerTree::TRUE,
+            create_extra_marker("foo"),
+        ));
+        marker.simplify();
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap()
+        );
+        assert_eq!(marker.conflict(), create_extra_marker("foo"));
+
+        // An unsatisfiable intersection makes both components `false`.
+        let mut marker = UniversalMarker::new(MarkerTree::TRUE, create_extra_marker("foo"));
+        marker.and(UniversalMarker::new(
+            MarkerTree::TRUE,
+            create_extra_marker("foo").negate(),
+        ));
+        assert!(marker.is_false());
+        assert!(marker.pep508().is_true());
+        marker.simplify();
+        assert!(marker.pep508().is_false());
+    }
+
+    #[test]
+    fn to_conflict_free() {
+        let pep508 = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+
+        let marker = UniversalMarker::new(pep508, create_extra_marker("foo"));
+        assert_eq!(marker.to_conflict_free(), (pep508, true));
+
+        let marker = UniversalMarker::new(pep508, ConflictMarker::TRUE);
+        assert_eq!(marker.to_conflict_free(), (pep508, false));
+    }
+
+    #[test]
+    fn disjuncts() {
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux' or sys_platform == 'win32'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        let disjuncts = marker.disjuncts();
+        assert_eq!(disjuncts.len(), 2);
+        assert!(disjuncts[0].is_disjoint(disjuncts[1]));
+        for disjunct in &disjuncts {
+            assert_eq!(disjunct.conflict(), create_extra_marker("foo"));
+        }
+        let mut union = UniversalMarker::FALSE;
+        for disjunct in disjuncts {
+            union.or(disjunct);
+        }
+        assert_eq!(union, marker);
+
+        // A conjunction is its own single disjunct.
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux' and python_version >= '3.10'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        assert_eq!(marker.disjuncts(), vec![marker]);
+
+        assert_eq!(
+            UniversalMarker::TRUE.disjuncts(),
+            vec![UniversalMarker::TRUE]
+        );
+        assert!(UniversalMarker::FALSE.disjuncts().is_empty());
+    }
+
+    #[test]
+    fn marker_interner() {
+        let mut interner = MarkerInterner::default();
+        let linux = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        // Built differently, but semantically equivalent.
+        let also_linux = UniversalMarker::from_combined(
+            MarkerTree::from_str("extra == 'extra-3-pkg-foo' and sys_platform == 'linux'").unwrap(),
+        );
+
+        let handle = interner.intern(linux);
+        assert_eq!(interner.intern(also_linux), handle);
+        assert_ne!(interner.intern(UniversalMarker::TRUE), handle);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(*interner.get(handle), linux);
+
+        interner.clear();
+        assert!(interner.is_empty());
+    }
+
+    #[test]
+    fn arbitrary_extra() {
+        let arbitrary = MarkerTree::expression(MarkerExpression::Extra {
+            operator: ExtraOperator::Equal,
+            name: uv_pep508::MarkerValueExtra::Arbitrary("extra-3-pkg-foo!".to_string()),
+        });
+        let mut marker = UniversalMarker::from_combined(arbitrary);
+        assert!(marker.is_false());
+
+        marker.assume_conflict_item(&create_extra_item("foo"));
+        assert!(marker.is_false());
+        marker.assume_not_conflict_item(&create_extra_item("foo"));
+        assert!(marker.is_false());
+        assert!(marker.pep508().is_false());
+    }
+
+    #[test]
+    fn covers_all_environments() {
+        let linux = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+        let marker = UniversalMarker::new(linux, ConflictMarker::TRUE);
+
+        let linux_forks = [
+            UniversalMarker::new(
+                MarkerTree::from_str("sys_platform == 'linux' and python_version < '3.10'")
+                    .unwrap(),
+                ConflictMarker::TRUE,
+            ),
+            UniversalMarker::new(
+                MarkerTree::from_str("sys_platform == 'linux' and python_version >= '3.10'")
+                    .unwrap(),
+                ConflictMarker::TRUE,
+            ),
+        ];
+        assert!(marker.covers_all_environments(&linux_forks));
+
+        let forks = [
+            UniversalMarker::new(linux, ConflictMarker::TRUE),
+            UniversalMarker::new(
+                MarkerTree::from_str("sys_platform == 'darwin'").unwrap(),
+                ConflictMarker::TRUE,
+            ),
+        ];
+        assert!(!marker.covers_all_environments(&forks));
+        assert!(!marker.covers_all_environments(&[]));
+        assert!(UniversalMarker::TRUE.covers_all_environments(&[]));
+
+        // A non-trivial conflict marker depends on the activated extras.
+        let marker = UniversalMarker::new(linux, create_extra_marker("foo"));
+        assert!(!marker.covers_all_environments(&linux_forks));
+    }
+
+    #[test]
+    fn restrict_to_extras() {
+        let pkg = create_package("pkg");
+        let active = [(pkg.clone(), create_extra("foo"))];
+        let forbidden = [(pkg.clone(), create_extra("bar"))];
+
+        // Only installed when `foo` is enabled and `bar` is not.
+        let conflict = create_extra_marker("foo").and(create_extra_marker("bar").negate());
+        let mut marker = UniversalMarker::new(MarkerTree::TRUE, conflict);
+        marker.restrict_to_extras(&active, &forbidden);
+        assert!(marker.is_true());
+
+        // Only installed when `bar` is enabled.
+        let mut marker = UniversalMarker::new(MarkerTree::TRUE, create_extra_marker("bar"));
+        marker.restrict_to_extras(&active, &forbidden);
+        assert!(marker.is_false());
+
+        // The PEP 508 marker is preserved.
+        let linux = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+        let mut marker = UniversalMarker::new(linux, conflict);
+        marker.restrict_to_extras(&active, &forbidden);
+        assert_eq!(marker, UniversalMarker::new(linux, ConflictMarker::TRUE));
+    }
+
+    /// A long chain of conjunctions stays as compact as the marker it
+    /// evaluates to.
+    #[test]
+    fn and_chain_stays_compact() {
+        let mut marker = UniversalMarker::TRUE;
+        for minor in 0..100 {
+            marker.and(UniversalMarker::from_combined(
+                MarkerTree::from_str(&format!(
+                    "sys_platform != 'platform{minor}' and python_full_version >= '3.{minor}'"
+                ))
+                .unwrap(),
+            ));
+            marker.and(UniversalMarker::from_combined(
+                MarkerTree::from_str("sys_platform == 'linux' or sys_platform == 'darwin'")
+                    .unwrap(),
+            ));
+        }
+
+        let expected = UniversalMarker::from_combined(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' or sys_platform == 'darwin') \
+                 and python_full_version >= '3.99'",
+            )
+            .unwrap(),
+        );
+        assert_eq!(marker, expected);
+        let clauses = marker.combined().to_dnf();
+        assert_eq!(clauses.len(), 2);
+        assert!(clauses.iter().all(|clause| clause.len() == 2));
+
+        for (version, platform) in [
+            ("3.99.0", "linux"),
+            ("3.98.0", "linux"),
+            ("3.99.0", "win32"),
+        ] {
+            let env = create_env(version, platform);
+            assert_eq!(evaluate_empty(marker, &env), evaluate_empty(expected, &env));
+        }
+    }
+
+    #[test]
+    fn implies() {
+        let linux = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            ConflictMarker::TRUE,
+        );
+        let linux_foo = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo"),
+        );
+
+        let mut implication = linux_foo;
+        implication.implies(linux);
+        assert!(implication.is_true());
+
+        let mut implication = linux;
+        implication.implies(linux_foo);
+        assert!(!implication.is_true());
+        assert!(!implication.is_false());
+    }
+
+    #[test]
+    fn pep508_strips_extras() {
+        let linux = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+        for marker in [
+            "sys_platform == 'linux' and extra == 'x'",
+            "sys_platform == 'linux' and extra != 'x'",
+            "(sys_platform == 'linux' and extra == 'x') or (sys_platform == 'linux' and extra == 'y')",
+        ] {
+            let marker = UniversalMarker::new(
+                MarkerTree::from_str(marker).unwrap(),
+                create_extra_marker("foo"),
+            );
+            assert_eq!(marker.pep508(), linux);
+        }
+    }
+
+    #[test]
+    fn as_conjuncts() {
+        assert!(UniversalMarker::TRUE.as_conjuncts().is_empty());
+        assert_eq!(
+            UniversalMarker::FALSE.as_conjuncts(),
+            vec![MarkerTree::FALSE]
+        );
+
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux' and python_version >= '3.9'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        let conjuncts = marker.as_conjuncts();
+        assert_eq!(conjuncts.len(), 3);
+        for expected in [
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            MarkerTree::from_str("python_full_version >= '3.9'").unwrap(),
+            UniversalMarker::new(MarkerTree::TRUE, create_extra_marker("foo")).combined(),
+        ] {
+            assert!(
+                conjuncts.contains(&expected),
+                "missing conjunct {expected:?}"
+            );
+        }
+
+        // A disjunction that isn't shared by all clauses is kept as one conjunct.
+        let marker = UniversalMarker::from_combined(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' or sys_platform == 'darwin') \
+                 and implementation_name == 'pypy'",
+            )
+            .unwrap(),
+        );
+        let conjuncts = marker.as_conjuncts();
+        assert_eq!(conjuncts.len(), 2);
+        assert!(
+            conjuncts.contains(&MarkerTree::from_str("implementation_name == 'pypy'").unwrap())
+        );
+        assert!(conjuncts.contains(
+            &MarkerTree::from_str("sys_platform == 'linux' or sys_platform == 'darwin'").unwrap()
+        ));
+
+        // The conjunction of the conjuncts is the marker itself.
+        let mut and = MarkerTree::TRUE;
+        for conjunct in conjuncts {
+            and.and(conjunct);
+        }
+        assert_eq!(and, marker.combined());
+    }
+
+    #[test]
+    fn approx_size() {
+        assert_eq!(UniversalMarker::TRUE.approx_size(), 0);
+        assert_eq!(UniversalMarker::FALSE.approx_size(), 0);
+
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux' or sys_platform == 'darwin'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        assert_eq!(marker.approx_size(), 4);
+    }
+
+    /// A set can declare a conflict between an extra of one package and a
+    /// group of another.
+    #[test]
+    fn mixed_extra_group_conflict() {
+        let foo = create_package("foo");
+        let bar = create_package("bar");
+        let a = create_extra("a");
+        let dev = GroupName::from_str("dev").unwrap();
+        let extra_item = ConflictItem::from((foo.clone(), a.clone()));
+        let group_item = ConflictItem::from((bar.clone(), dev.clone()));
+        let conflicts =
+            create_conflicts([ConflictSet::pair(extra_item.clone(), group_item.clone())]);
+
+        let extra_marker = ConflictMarker::extra(&foo, &a);
+        let group_marker = ConflictMarker::group(&bar, &dev);
+        let conflicts_marker = ConflictMarker::from_conflicts(&conflicts);
+        assert_eq!(conflicts_marker, extra_marker.and(group_marker).negate());
+
+        // Activating the extra rules out the group, and vice versa.
+        let env = create_env("3.12.0", "linux");
+        let marker = UniversalMarker::new(MarkerTree::TRUE, conflicts_marker);
+        let evaluate = |extras: &[(PackageName, ExtraName)],
+                        groups: &[(PackageName, GroupName)]| {
+            marker.evaluate(
+                &env,
+                std::iter::empty::<&PackageName>(),
+                extras.iter().map(|(package, extra)| (package, extra)),
+                groups.iter().map(|(package, group)| (package, group)),
+            )
+        };
+        assert!(evaluate(&[(foo.clone(), a.clone())], &[]));
+        assert!(evaluate(&[], &[(bar.clone(), dev.clone())]));
+        assert!(!evaluate(
+            &[(foo.clone(), a.clone())],
+            &[(bar.clone(), dev.clone())]
+        ));
+
+        let mut assumed = marker;
+        assumed.assume_conflict_item(&extra_item);
+        assert_eq!(
+            assumed,
+            UniversalMarker::new(MarkerTree::TRUE, group_marker.negate())
+        );
+        let mut assumed = marker;
+        assumed.assume_conflict_item(&group_item);
+        assert_eq!(
+            assumed,
+            UniversalMarker::new(MarkerTree::TRUE, extra_marker.negate())
+        );
+
+        // Imbibing the conflict drops the redundant condition on the group.
+        let mut marker =
+            UniversalMarker::new(MarkerTree::TRUE, extra_marker.and(group_marker.negate()));
+        marker.imbibe(conflicts_marker);
+        assert_eq!(marker, UniversalMarker::new(MarkerTree::TRUE, extra_marker));
+    }
+
+    #[test]
+    fn pairwise_conflict_encoder() {
+        let conflicts = create_conflicts([
+            create_set(["foo", "bar", "baz"]),
+            create_set(["cpu", "cu124"]),
+        ]);
+        let not_both =
+            |a: &str, b: &str| create_extra_marker(a).and(create_extra_marker(b)).negate();
+        let expected = not_both("foo", "bar")
+            .and(not_both("foo", "baz"))
+            .and(not_both("bar", "baz"))
+            .and(not_both("cpu", "cu124"));
+        assert_eq!(PairwiseConflictEncoder.encode(&conflicts), expected);
+        assert_eq!(
+            PairwiseConflictEncoder.encode(&conflicts),
+            ConflictMarker::from_conflicts(&conflicts)
+        );
+        assert!(
+            PairwiseConflictEncoder
+                .encode(&Conflicts::empty())
+                .is_true()
+        );
+    }
+
+    #[test]
+    fn requires_python_marker() {
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and python_full_version >= '3.11') \
+                 or (sys_platform == 'win32' and python_version < '3.9')",
+            )
+            .unwrap(),
+            create_extra_marker("foo"),
+        );
+        assert_eq!(
+            marker.requires_python_marker(),
+            MarkerTree::from_str("python_full_version >= '3.11' or python_full_version < '3.9'")
+                .unwrap()
+        );
+
+        // Markers without any Python version expressions aren't restricted.
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        assert!(marker.requires_python_marker().is_true());
+        assert!(UniversalMarker::FALSE.requires_python_marker().is_false());
+    }
+
+    #[test]
+    fn is_partition() {
+        let marker = |s: &str| UniversalMarker::from_combined(MarkerTree::from_str(s).unwrap());
+
+        let result = UniversalMarker::is_partition(&[
+            marker("sys_platform == 'linux'"),
+            marker("sys_platform != 'linux'"),
+        ]);
+        assert!(result.is_partition());
+
+        let result = UniversalMarker::is_partition(&[
+            marker("sys_platform == 'linux'"),
+            marker("sys_platform != 'win32'"),
+            marker("sys_platform == 'win32'"),
+        ]);
+        assert!(!result.is_partition());
+        assert_eq!(result.overlapping, vec![(0, 1)]);
+        assert!(result.uncovered.is_false());
+
+        let result = UniversalMarker::is_partition(&[
+            marker("sys_platform == 'linux'"),
+            marker("sys_platform == 'darwin'"),
+        ]);
+        assert!(!result.is_partition());
+        assert!(result.overlapping.is_empty());
+        assert_eq!(
+            result.uncovered,
+            marker("sys_platform != 'linux' and sys_platform != 'darwin'")
+        );
+    }
+
+    #[test]
+    fn evaluate_all() {
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str("python_version >= '3.11'").unwrap(),
+            create_extra_marker("foo").or(create_extra_marker("bar").negate()),
+        );
+        let pkg = create_package("pkg");
+        let foo = vec![(pkg.clone(), create_extra("foo"))];
+        let bar = vec![(pkg.clone(), create_extra("bar"))];
+        let envs = [
+            (create_env("3.10.0", "linux"), vec![]),
+            (create_env("3.11.2", "linux"), vec![]),
+            (create_env("3.12.0", "linux"), foo),
+            (create_env("3.12.0", "linux"), bar),
+            (
+                create_env("3.9.0", "linux"),
+                vec![(pkg, create_extra("foo"))],
+            ),
+        ];
+
+        let expected = envs
+            .iter()
+            .map(|(env, extras)| {
+                marker.evaluate(
+                    env,
+                    std::iter::empty::<&PackageName>(),
+                    extras.iter().map(|(package, extra)| (package, extra)),
+                    std::iter::empty::<(&PackageName, &GroupName)>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected, vec![false, true, true, false, false]);
+        assert_eq!(marker.evaluate_all(&envs), expected);
+
+        assert_eq!(UniversalMarker::TRUE.evaluate_all(&envs), vec![true; 5]);
+    }
+
+    #[test]
+    fn split_platforms() {
+        let marker = UniversalMarker::new(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and python_full_version >= '3.10') \
+                 or (sys_platform == 'darwin' and platform_machine == 'arm64') \
+                 or (sys_platform != 'win32' and implementation_name == 'pypy')",
+            )
+            .unwrap(),
+            create_extra_marker("foo"),
+        );
+        let pieces = marker.split_platforms(&["linux", "darwin", "win32"]);
+
+        let restricted = |pep508: &str| {
+            UniversalMarker::new(
+                MarkerTree::from_str(pep508).unwrap(),
+                create_extra_marker("foo"),
+            )
+        };
+        assert_eq!(
+            pieces,
+            vec![
+                (
+                    "linux".to_string(),
+                    restricted("python_full_version >= '3.10' or implementation_name == 'pypy'")
+                ),
+                (
+                    "darwin".to_string(),
+                    restricted("platform_machine == 'arm64' or implementation_name == 'pypy'")
+                ),
+            ]
+        );
+    }
+
+    /// Known `platform_system` aliases are rewritten as `sys_platform` when
+    /// a marker is constructed, so markers differing only in which of the two
+    /// keys they use are already equal.
+    #[test]
+    fn platform_system_aliases_are_canonical() {
+        let universal = |pep508: &str| {
+            UniversalMarker::new(
+                MarkerTree::from_str(pep508).unwrap(),
+                create_extra_marker("foo"),
+            )
+        };
+
+        assert_eq!(
+            universal(
+                "(platform_system == 'Darwin' and python_full_version >= '3.10') \
+                 or platform_system != 'Windows'"
+            ),
+            universal(
+                "(sys_platform == 'darwin' and python_full_version >= '3.10') \
+                 or sys_platform != 'win32'"
+            ),
+        );
+        assert_eq!(
+            universal("platform_system == 'Linux'"),
+            universal("sys_platform == 'linux'")
+        );
+
+        // Values without an exact equivalent are left as is.
+        assert_ne!(
+            universal("platform_system == 'FreeBSD'"),
+            universal("sys_platform == 'freebsd'")
+        );
+    }
+
+    #[test]
+    fn from_bool() {
+        assert_eq!(UniversalMarker::from(true), UniversalMarker::TRUE);
+        assert_eq!(UniversalMarker::from(false), UniversalMarker::FALSE);
+        assert!(UniversalMarker::from(true).is_true());
+        assert!(UniversalMarker::from(false).is_false());
+    }
+
+    #[test]
+    fn assume_false_extra() {
+        let foo = create_extra("foo");
+        let linux = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+
+        // Edges gated on the extra are removed.
+        let mut marker = UniversalMarker::new(linux, create_extra_marker("foo"));
+        marker.assume_false_extra(&foo);
+        assert!(marker.is_false());
+
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'linux' and extra == 'foo'").unwrap(),
+        );
+        marker.assume_false_extra(&foo);
+        assert!(marker.is_false());
+
+        // Edges gated on the absence of the extra are kept.
+        let mut marker = UniversalMarker::new(linux, create_extra_marker("foo").negate());
+        marker.assume_false_extra(&foo);
+        assert_eq!(marker, UniversalMarker::new(linux, ConflictMarker::TRUE));
+
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'linux' and extra != 'foo'").unwrap(),
+        );
+        marker.assume_false_extra(&foo);
+        assert_eq!(marker, UniversalMarker::new(linux, ConflictMarker::TRUE));
+
+        // Other extras are untouched.
+        let conflict = create_extra_marker("foo").or(create_extra_marker("bar"));
+        let mut marker = UniversalMarker::new(linux, conflict);
+        marker.assume_false_extra(&foo);
+        assert_eq!(
+            marker,
+            UniversalMarker::new(linux, create_extra_marker("bar"))
+        );
+    }
+
+    #[test]
+    fn simplify_conflict_extras_with() {
+        let pkg = create_package("pkg");
+        let active = [
+            encode_package_extra(&pkg, &create_extra("foo")),
+            encode_package_extra(&pkg, &create_extra("bar")),
+        ];
+        let linux = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+
+        // Both extras are assumed active, so only the PEP 508 marker remains.
+        let conflict = create_extra_marker("foo").and(create_extra_marker("bar"));
+        let mut marker = UniversalMarker::new(linux, conflict);
+        marker.simplify_conflict_extras_with(|extra| active.contains(extra));
+        assert_eq!(marker, UniversalMarker::new(linux, ConflictMarker::TRUE));
+
+        // Extras outside of the predicate are left as-is.
+        let conflict = create_extra_marker("foo").and(create_extra_marker("baz"));
+        let mut marker = UniversalMarker::new(linux, conflict);
+        marker.simplify_conflict_extras_with(|extra| active.contains(extra));
+        assert_eq!(
+            marker,
+            UniversalMarker::new(linux, create_extra_marker("baz"))
+        );
+    }
+
+    #[test]
+    fn map_extras() {
+        let rename = |extra: &ExtraName| {
+            if *extra == create_extra("old") {
+                create_extra("new")
+            } else {
+                extra.clone()
+            }
+        };
+        let group =
+            ConflictMarker::group(&create_package("pkg"), &GroupName::from_str("old").unwrap());
+
+        let pep508 = MarkerTree::from_str("extra == 'old' and sys_platform == 'linux'").unwrap();
+        let conflict = create_extra_marker("old").or(create_extra_marker("other"));
+        let mut marker = UniversalMarker::from_combined(pep508);
+        marker.and(UniversalMarker::new(MarkerTree::TRUE, conflict.and(group)));
+        marker.map_extras(rename);
+
+        let pep508 = MarkerTree::from_str("extra == 'new' and sys_platform == 'linux'").unwrap();
+        let conflict = create_extra_marker("new").or(create_extra_marker("other"));
+        let mut expected = UniversalMarker::from_combined(pep508);
+        expected.and(UniversalMarker::new(MarkerTree::TRUE, conflict.and(group)));
+        assert_eq!(marker, expected);
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap()
+        );
+    }
+
+    #[test]
+    fn display_precision() {
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'linux' and python_full_version >= '3.12'")
+                .unwrap(),
+        );
+        marker.and(UniversalMarker::new(
+            MarkerTree::TRUE,
+            create_extra_marker("foo"),
+        ));
+        let full = format!("{marker:?}");
+        assert_eq!(format!("{marker}"), full);
+
+        let capped = format!("{marker:.40}");
+        assert!(capped.chars().count() <= 40, "{capped}");
+        let head = capped.strip_suffix('…').unwrap();
+        assert!(!head.is_empty(), "{capped}");
+        assert!(full.starts_with(head), "{capped}");
+        // The cut is between two expressions.
+        assert!(full[head.len()..].starts_with(" and "), "{capped}");
+
+        // If not even the first expression fits, the marker isn't cut in the
+        // middle of it.
+        assert_eq!(format!("{marker:.20}"), "…");
+
+        // Markers that fit aren't truncated.
+        assert_eq!(format!("{:.20}", UniversalMarker::TRUE), "true");
+        assert_eq!(format!("{marker:.1000}"), full);
+    }
+
+    #[test]
+    fn truncate_marker_parenthesized() {
+        let marker = "(sys_platform == 'linux' or sys_platform == 'darwin') and extra == 'foo'";
+
+        // A closing parenthesis that fits is kept.
+        assert_eq!(
+            truncate_marker(marker, 54),
+            "(sys_platform == 'linux' or sys_platform == 'darwin')…"
+        );
+
+        // Otherwise, the marker is cut within the parentheses.
+        assert_eq!(truncate_marker(marker, 53), "(sys_platform == 'linux'…");
+    }
+
+    #[test]
+    fn pretty_conflict() {
+        let conflicts = create_conflicts([create_set(["foo", "bar"])]);
+        let conflicts_marker = ConflictMarker::from_conflicts(&conflicts);
+        let pkg = create_package("pkg");
+        let dev = GroupName::from_str("dev").unwrap();
+
+        assert_eq!(UniversalMarker::TRUE.pretty_conflict(), None);
+        assert_eq!(
+            UniversalMarker::new(
+                MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+                ConflictMarker::TRUE
+            )
+            .pretty_conflict(),
+            None
+        );
+
+        // Imbibing the conflicts turns `not foo` into `not foo or bar`: the
+        // conflicts rule out `foo` and `bar` being active together, so the
+        // marker may as well be satisfied then, too.
+        let mut marker =
+            UniversalMarker::new(MarkerTree::TRUE, create_extra_marker("foo").negate());
+        marker.imbibe(conflicts_marker);
+        let rendered = marker.pretty_conflict().unwrap();
+        assert!(
+            matches!(
+                rendered.as_str(),
+                "without extra 'foo' or with extra 'bar'"
+                    | "with extra 'bar' or without extra 'foo'"
+            ),
+            "{rendered}"
+        );
+
+        // Conjunctions within a disjunction are parenthesized.
+        let marker = UniversalMarker::new(
+            MarkerTree::TRUE,
+            create_extra_marker("baz")
+                .and(ConflictMarker::group(&pkg, &dev).negate())
+                .or(create_extra_marker("qux")),
+        );
+        let rendered = marker.pretty_conflict().unwrap();
+        let conjunction = [
+            "(with extra 'baz' and when group 'dev' is inactive)",
+            "(when group 'dev' is inactive and with extra 'baz')",
+        ];
+        assert!(
+            conjunction.iter().any(|conjunction| {
+                rendered == format!("{conjunction} or with extra 'qux'")
+                    || rendered == format!("with extra 'qux' or {conjunction}")
+            }),
+            "{rendered}"
+        );
+
+        // Plain PEP 508 extras are rendered with their raw name.
+        let marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'linux' and extra != 'docs'").unwrap(),
+        );
+        assert_eq!(
+            marker.pretty_conflict().as_deref(),
+            Some("without extra 'docs'")
+        );
+    }
+
+    #[test]
+    fn explain_conflict() {
+        let conflicts = create_conflicts([create_set(["foo", "bar"])]);
+        let conflicts_marker = ConflictMarker::from_conflicts(&conflicts);
+
+        assert_eq!(UniversalMarker::TRUE.explain_conflict(&conflicts), None);
+
+        // Since `foo` and `bar` conflict, `bar` is necessarily inactive whenever
+        // `foo` is active, so imbibing the conflicts drops the redundant
+        // condition on `bar`.
+        let mut marker = UniversalMarker::new(
+            MarkerTree::TRUE,
+            create_extra_marker("foo").and(create_extra_marker("bar").negate()),
+        );
+        marker.imbibe(conflicts_marker);
+        assert_eq!(
+            marker.explain_conflict(&conflicts).as_deref(),
+            Some(
+                "only installed when extra 'foo' is active \
+                 (extras 'bar' and 'foo' are declared mutually exclusive)"
+            )
+        );
+
+        // Conditions on items outside of any conflict set are explained without
+        // a declared exclusion.
+        let marker = UniversalMarker::new(MarkerTree::TRUE, create_extra_marker("baz").negate());
+        assert_eq!(
+            marker.explain_conflict(&conflicts).as_deref(),
+            Some("only installed when extra 'baz' is inactive")
+        );
+
+        // Plain PEP 508 extras are explained with their raw name.
+        let marker =
+            UniversalMarker::from_combined(MarkerTree::from_str("extra == 'docs'").unwrap());
+        assert_eq!(
+            marker.explain_conflict(&conflicts).as_deref(),
+            Some("only installed when extra 'docs' is active")
+        );
+    }
+
+    /// Equality is already semantic, so snapshot tests can compare markers
+    /// with `assert_eq!` without pinning the shape of the marker trees, either
+    /// directly or through [`SemanticMarker`].
+    #[test]
+    fn equality_is_semantic() {
+        let distributed = UniversalMarker::new(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and python_version >= '3.10') \
+                 or (sys_platform == 'linux' and implementation_name == 'pypy')",
+            )
+            .unwrap(),
+            create_extra_marker("foo").or(create_extra_marker("bar")),
+        );
+        let factored = UniversalMarker::new(
+            MarkerTree::from_str(
+                "sys_platform == 'linux' \
+                 and (implementation_name == 'pypy' or python_full_version >= '3.10')",
+            )
+            .unwrap(),
+            create_extra_marker("bar").or(create_extra_marker("foo")),
+        );
+        assert_eq!(distributed, factored);
+        assert_eq!(
+            SemanticMarker::from(distributed),
+            SemanticMarker::from(factored)
+        );
+
+        // A marker built up one operation at a time is equal, too.
+        let mut incremental = UniversalMarker::new(
+            MarkerTree::from_str("python_version >= '3.10'").unwrap(),
+            ConflictMarker::TRUE,
+        );
+        incremental.or(UniversalMarker::new(
+            MarkerTree::from_str("implementation_name == 'pypy'").unwrap(),
+            ConflictMarker::TRUE,
+        ));
+        incremental.and(UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo").or(create_extra_marker("bar")),
+        ));
+        assert_eq!(incremental, factored);
+
+        let different = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux' and python_version >= '3.10'").unwrap(),
+            create_extra_marker("foo").or(create_extra_marker("bar")),
+        );
+        assert_ne!(different, factored);
+        assert_ne!(
+            SemanticMarker::from(different),
+            SemanticMarker::from(factored)
+        );
+    }
+
+    #[test]
+    fn satisfiable_extra_sets() {
+        let pkg = create_package("pkg");
+        let universe = ["cpu", "cu124", "py312", "py313"].map(create_extra);
+        let conflicts =
+            create_conflicts([create_set(["cpu", "cu124"]), create_set(["py312", "py313"])]);
+        let marker =
+            UniversalMarker::new(MarkerTree::TRUE, ConflictMarker::from_conflicts(&conflicts));
+
+        let sets = marker.satisfiable_extra_sets(&pkg, &universe);
+        let expected = [
+            ["cpu", "py312"],
+            ["cpu", "py313"],
+            ["cu124", "py312"],
+            ["cu124", "py313"],
+        ]
+        .map(|set| set.map(create_extra).into_iter().collect::<FxHashSet<_>>());
+        assert_eq!(sets, expected);
+
+        // Without any conflicts, all extras can be activated together.
+        let sets = UniversalMarker::TRUE.satisfiable_extra_sets(&pkg, &universe);
+        assert_eq!(
+            sets,
+            vec![universe.iter().cloned().collect::<FxHashSet<_>>()]
+        );
+
+        // An unsatisfiable marker has no sets at all.
+        assert!(
+            UniversalMarker::FALSE
+                .satisfiable_extra_sets(&pkg, &universe)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn overlaps_extra() {
+        let pkg = create_package("pkg");
+        let foo = create_extra("foo");
+
+        // An edge that forbids `foo` can't be relevant when `foo` is active.
+        let forbidding = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo").negate(),
+        );
+        assert!(!forbidding.overlaps_extra(&pkg, &foo));
+
+        // Edges that don't depend on `foo`, or require it, are.
+        let unrelated = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("bar"),
+        );
+        assert!(unrelated.overlaps_extra(&pkg, &foo));
+        let requiring = UniversalMarker::new(MarkerTree::TRUE, create_extra_marker("foo"));
+        assert!(requiring.overlaps_extra(&pkg, &foo));
+        assert!(!UniversalMarker::FALSE.overlaps_extra(&pkg, &foo));
+
+        // The extra is scoped to its package.
+        assert!(forbidding.overlaps_extra(&create_package("other"), &foo));
+    }
+
+    #[test]
+    fn and_fork() {
+        let linux = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+        );
+        let edge = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux' and python_version >= '3.9'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        assert_eq!(
+            edge.and_fork(linux),
+            UniversalMarker::new(
+                MarkerTree::from_str("python_version >= '3.9'").unwrap(),
+                create_extra_marker("foo"),
+            )
+        );
+
+        // Clauses outside of the fork are dropped.
+        let edge = UniversalMarker::from_combined(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and python_version >= '3.9') \
+                 or (sys_platform == 'darwin' and implementation_name == 'pypy')",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            edge.and_fork(linux).pep508(),
+            MarkerTree::from_str("python_version >= '3.9'").unwrap()
+        );
+        let darwin = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'darwin'").unwrap(),
+        );
+        assert_eq!(
+            edge.and_fork(darwin).pep508(),
+            MarkerTree::from_str("implementation_name == 'pypy'").unwrap()
+        );
+    }
+
+    #[test]
+    fn strip_redundant_python_bounds() {
+        let requires_python =
+            RequiresPython::greater_than_equal_version(&Version::from_str("3.8").unwrap());
+
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("python_version >= '3.8' and sys_platform == 'linux'").unwrap(),
+        );
+        marker.strip_redundant_python_bounds(&requires_python);
+        assert_eq!(
+            marker.combined(),
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap()
+        );
+
+        // Bounds that are narrower than `requires-python` are retained, as is
+        // the conflict marker.
+        let original = UniversalMarker::new(
+            MarkerTree::from_str("python_version >= '3.10' and sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        let mut marker = original;
+        marker.strip_redundant_python_bounds(&requires_python);
+        assert_eq!(marker, original);
+    }
+
+    #[test]
+    fn common_factor() {
+        let a = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'linux' and python_version >= '3.10'").unwrap(),
+        );
+        let b = UniversalMarker::from_combined(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and python_version < '3.9') \
+                 or (sys_platform == 'linux' and implementation_name == 'pypy')",
+            )
+            .unwrap(),
+        );
+        let factor = UniversalMarker::common_factor(a, b);
+        assert_eq!(
+            factor,
+            UniversalMarker::from_combined(
+                MarkerTree::from_str("sys_platform == 'linux'").unwrap()
+            )
+        );
+
+        // The factor is implied by both markers.
+        for marker in [a, b] {
+            assert!(marker.is_disjoint(UniversalMarker::from_combined(factor.combined().negate())));
+        }
+
+        // Without a shared clause, the factor is trivial.
+        let c = UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'darwin'").unwrap(),
+        );
+        assert!(UniversalMarker::common_factor(a, c).is_true());
+    }
+
+    #[test]
+    fn assume_environment_keys() {
+        let linux = [(
+            MarkerValue::MarkerEnvString(MarkerValueString::SysPlatform),
+            "linux".to_string(),
+        )];
+
+        let mut marker = UniversalMarker::new(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and python_version >= '3.10') \
+                 or (sys_platform == 'darwin' and python_version < '3.9') \
+                 or (sys_platform != 'win32' and implementation_name == 'pypy')",
+            )
+            .unwrap(),
+            create_extra_marker("foo"),
+        );
+        marker.assume_environment_keys(&linux);
+        assert_eq!(
+            marker,
+            UniversalMarker::new(
+                MarkerTree::from_str("python_version >= '3.10' or implementation_name == 'pypy'")
+                    .unwrap(),
+                create_extra_marker("foo"),
+            )
+        );
+
+        // `platform_system` isn't listed, so it remains symbolic, even though
+        // it is disjoint with `sys_platform == 'linux'`.
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("platform_system == 'Darwin' or sys_platform == 'win32'").unwrap(),
+        );
+        marker.assume_environment_keys(&linux);
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("platform_system == 'Darwin'").unwrap()
+        );
+
+        // `python_version` assignments apply to `python_full_version`, too.
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("python_full_version >= '3.10' and sys_platform == 'linux'")
+                .unwrap(),
+        );
+        marker.assume_environment_keys(&[(
+            MarkerValue::MarkerEnvVersion(MarkerValueVersion::PythonVersion),
+            "3.11".to_string(),
+        )]);
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap()
+        );
+
+        // A `python_version` assignment stands for every patch release, so a
+        // bound on the patch version remains symbolic.
+        let mut marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("python_full_version >= '3.11.1' and sys_platform == 'linux'")
+                .unwrap(),
+        );
+        marker.assume_environment_keys(&[(
+            MarkerValue::MarkerEnvVersion(MarkerValueVersion::PythonVersion),
+            "3.11".to_string(),
+        )]);
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("python_full_version >= '3.11.1' and sys_platform == 'linux'")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn assume_python_version() {
+        let py311 = Version::from_str("3.11").unwrap();
+        let py39 = Version::from_str("3.9").unwrap();
+
+        for marker in ["python_version >= '3.10'", "python_full_version >= '3.10'"] {
+            let marker = UniversalMarker::from_combined(MarkerTree::from_str(marker).unwrap());
+
+            let mut assumed = marker;
+            assumed.assume_python_version(&py311);
+            assert!(assumed.is_true());
+
+            let mut assumed = marker;
+            assumed.assume_python_version(&py39);
+            assert!(assumed.is_false());
+        }
+
+        // `3.11` stands for every `3.11.x` release, so a bound on the patch
+        // version stays undecided. A full version decides it.
+        let marker = UniversalMarker::from_combined(
+            MarkerTree::from_str("python_full_version >= '3.11.1'").unwrap(),
+        );
+        let mut assumed = marker;
+        assumed.assume_python_version(&py311);
+        assert_eq!(assumed, marker);
+        let mut assumed = marker;
+        assumed.assume_python_version(&Version::from_str("3.11.2").unwrap());
+        assert!(assumed.is_true());
+        let mut assumed = marker;
+        assumed.assume_python_version(&Version::from_str("3.11.0").unwrap());
+        assert!(assumed.is_false());
+
+        // Other expressions, including conflict markers, are preserved.
+        let mut marker = UniversalMarker::new(
+            MarkerTree::from_str(
+                "(python_version == '3.11' and sys_platform == 'linux') \
+                 or (python_version < '3.10' and sys_platform == 'darwin')",
+            )
+            .unwrap(),
+            create_extra_marker("foo"),
+        );
+        marker.assume_python_version(&py311);
+        assert_eq!(
+            marker,
+            UniversalMarker::new(
+                MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+                create_extra_marker("foo"),
+            )
+        );
+    }
+
+    #[test]
+    fn is_conflict_trivial() {
+        let linux = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+        assert!(UniversalMarker::TRUE.is_conflict_trivial());
+        assert!(UniversalMarker::new(linux, ConflictMarker::TRUE).is_conflict_trivial());
+        assert!(!UniversalMarker::new(linux, create_extra_marker("foo")).is_conflict_trivial());
+        assert!(!UniversalMarker::FALSE.is_conflict_trivial());
+    }
+
+    #[test]
+    fn or_asymmetric_conflicts() {
+        let linux = create_env("3.12.1", "linux");
+        let darwin = create_env("3.12.1", "darwin");
+        let pkg = create_package("pkg");
+        let foo = create_extra("foo");
+
+        let mut marker = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        marker.or(UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'darwin'").unwrap(),
+            ConflictMarker::TRUE,
+        ));
+
+        // The conflict marker of the Linux branch is retained.
+        assert!(!marker.is_conflict_trivial());
+        assert!(!evaluate_empty(marker, &linux));
+        assert!(marker.evaluate(
+            &linux,
+            std::iter::empty::<&PackageName>(),
+            [(&pkg, &foo)].into_iter(),
+            std::iter::empty::<(&PackageName, &GroupName)>(),
+        ));
+        // The Darwin branch doesn't depend on any extras.
+        assert!(evaluate_empty(marker, &darwin));
+        assert_eq!(
+            marker.pep508(),
+            MarkerTree::from_str("sys_platform == 'linux' or sys_platform == 'darwin'").unwrap()
+        );
+    }
+
+    #[test]
+    fn minimize() {
+        let verbose = UniversalMarker::from_combined(
+            MarkerTree::from_str(
+                "(sys_platform == 'linux' and python_version >= '3.9') \
+                 or (sys_platform == 'linux' and python_version < '3.9') \
+                 or (sys_platform == 'linux' and extra == 'extra-3-pkg-foo')",
+            )
+            .unwrap(),
+        );
+        let minimized = verbose.minimize().unwrap();
+        assert_eq!(
+            minimized,
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap()
+        );
+        assert_eq!(
+            minimized.try_to_string().as_deref(),
+            Some("sys_platform == 'linux'")
+        );
+
+        let conflicting = UniversalMarker::new(
+            MarkerTree::from_str("sys_platform == 'linux'").unwrap(),
+            create_extra_marker("foo"),
+        );
+        assert!(conflicting.minimize().is_err());
+
+        // The conflict marker alone is trivial, but the Linux branch requires `foo`.
+        let mut correlated = conflicting;
+        correlated.or(UniversalMarker::from_combined(
+            MarkerTree::from_str("sys_platform == 'darwin'").unwrap(),
+        ));
+        assert!(correlated.is_conflict_trivial());
+        assert!(correlated.minimize().is_err());
+    }
+
+    #[test]
+    fn from_parts_validated() {
+        let linux = MarkerTree::from_str("sys_platform == 'linux'").unwrap();
+        let conflict =
+            MarkerTree::from_str("extra == 'extra-3-pkg-foo' and extra != 'extra-3-pkg-bar'")
+                .unwrap();
+        let marker = UniversalMarker::from_parts_validated(linux, conflict).unwrap();
+        assert_eq!(
+            marker,
+            UniversalMarker::new(
+                linux,
+                create_extra_marker("foo").and(create_extra_marker("bar").negate())
+            )
+        );
+
+        let conflict =
+            MarkerTree::from_str("extra == 'extra-3-pkg-foo' or sys_platform == 'linux'").unwrap();
+        assert!(matches!(
+            UniversalMarker::from_parts_validated(MarkerTree::TRUE, conflict),
+            Err(MarkerPartError::NonConflictExpression(_))
+        ));
+    }
 }
@@ -629,13 +629,16 @@ pub struct ToolUv {
     /// for a project, taking into account that certain combinations of extras and
     /// groups are mutually exclusive. In exchange, installation will fail if a
     /// user attempts to activate both conflicting extras.
+    ///
+    /// A set may also be written as a table with `items` and `requires-one = true`,
+    /// in which case installation will also fail if none of its items are activated.
     #[cfg_attr(
         feature = "schemars",
         schemars(description = "A list of sets of conflicting groups or extras.")
     )]
     #[option(
         default = r#"[]"#,
-        value_type = "list[list[dict]]",
+        value_type = "list[list[dict] | dict]",
         example = r#"
             # Require that `package[extra1]` and `package[extra2]` are resolved
             # in different forks so that they cannot conflict with one another.
@@ -655,6 +658,15 @@ pub struct ToolUv {
                     { group = "group2" },
                 ]
             ]
+
+            # Require that exactly one of the extras `cpu` and `gpu` is activated
+            # when installing the project.
+            conflicts = [
+                { requires-one = true, items = [
+                    { extra = "cpu" },
+                    { extra = "gpu" },
+                ] }
+            ]
         "#
     )]
     pub conflicts: Option<SchemaConflicts>,
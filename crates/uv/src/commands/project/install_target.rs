@@ -9,7 +9,7 @@ use rustc_hash::FxHashSet;
 use uv_configuration::{Constraints, DependencyGroupsWithDefaults, ExtrasSpecification};
 use uv_distribution_types::Index;
 use uv_normalize::{ExtraName, PackageName};
-use uv_pypi_types::{DependencyGroupSpecifier, LenientRequirement, VerbatimParsedUrl};
+use uv_pypi_types::{Conflicts, DependencyGroupSpecifier, LenientRequirement, VerbatimParsedUrl};
 use uv_resolver::{Installable, Lock, Package};
 use uv_scripts::Pep723Script;
 use uv_workspace::Workspace;
@@ -371,6 +371,19 @@ impl<'lock> InstallTarget<'lock> {
         Ok(())
     }
 
+    /// Return the conflicts declared by the target.
+    ///
+    /// Unlike the conflicts in the lockfile, these retain whether a set requires one of its items
+    /// to be activated.
+    pub(crate) fn conflicts(&self) -> Conflicts {
+        match self {
+            Self::Project { workspace, .. } => workspace.conflicts(),
+            Self::Workspace { workspace, .. } => workspace.conflicts(),
+            Self::NonProjectWorkspace { workspace, .. } => workspace.conflicts(),
+            Self::Script { .. } => Conflicts::empty(),
+        }
+    }
+
     /// Returns the names of all packages in the workspace that will be installed.
     ///
     /// Note this only includes workspace members.
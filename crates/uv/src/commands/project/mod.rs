@@ -111,6 +111,12 @@ pub(crate) enum ProjectError {
     #[error(transparent)]
     Conflict(#[from] ConflictError),
 
+    #[error(
+        "One of the declared conflicts must be enabled: {{{}}}",
+        format_conflict_set(_0)
+    )]
+    MissingConflict(ConflictSet),
+
     #[error(
         "The requested interpreter resolved to Python {_0}, which is incompatible with the project's Python requirement: `{_1}`{}",
         format_optional_requires_python_sources(_2, *_3)
@@ -286,15 +292,7 @@ pub(crate) struct ConflictError {
 impl std::fmt::Display for ConflictError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // Format the set itself.
-        let set = self
-            .set
-            .iter()
-            .map(|item| match item.kind() {
-                ConflictKind::Project => format!("{}", item.package()),
-                ConflictKind::Extra(extra) => format!("`{}[{}]`", item.package(), extra),
-                ConflictKind::Group(group) => format!("`{}:{}`", item.package(), group),
-            })
-            .join(", ");
+        let set = format_conflict_set(&self.set);
 
         // If all the conflicts are of the same kind, show a more succinct error.
         if self
@@ -377,6 +375,17 @@ impl std::fmt::Display for ConflictError {
 
 impl std::error::Error for ConflictError {}
 
+/// Format the items of a [`ConflictSet`] for display in an error message.
+fn format_conflict_set(set: &ConflictSet) -> String {
+    set.iter()
+        .map(|item| match item.kind() {
+            ConflictKind::Project => format!("{}", item.package()),
+            ConflictKind::Extra(extra) => format!("`{}[{}]`", item.package(), extra),
+            ConflictKind::Group(group) => format!("`{}:{}`", item.package(), group),
+        })
+        .join(", ")
+}
+
 /// A [`SharedState`] instance to use for universal resolution.
 #[derive(Default, Clone)]
 pub(crate) struct UniversalState(SharedState);
@@ -2554,6 +2563,34 @@ pub(crate) fn detect_conflicts(
     Ok(())
 }
 
+/// Validate that we are trying to install one of the extras or groups of
+/// each set of conflicts that requires one of its items to be activated.
+#[allow(clippy::result_large_err)]
+pub(crate) fn detect_missing_conflicts(
+    target: &InstallTarget,
+    extras: &ExtrasSpecification,
+    groups: &DependencyGroupsWithDefaults,
+) -> Result<(), ProjectError> {
+    let packages = target.packages(extras, groups);
+    let conflicts = target.conflicts();
+    let violations = conflicts.requires_one_violations(|item| {
+        packages.contains(item.package())
+            && match item.kind() {
+                ConflictKind::Project => groups.prod(),
+                ConflictKind::Extra(extra) => extras.contains(extra),
+                ConflictKind::Group(group) => groups.contains(group),
+            }
+    });
+    for set in violations {
+        // Ignore sets that are not in the install targets.
+        if !set.iter().any(|item| packages.contains(item.package())) {
+            continue;
+        }
+        return Err(ProjectError::MissingConflict(set.clone()));
+    }
+    Ok(())
+}
+
 /// Determine the [`RequirementsSpecification`] for a script.
 #[allow(clippy::result_large_err)]
 pub(crate) fn script_specification(